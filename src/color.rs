@@ -0,0 +1,243 @@
+use anyhow::{anyhow, Result};
+use crossterm::style::Color;
+use std::{collections::HashMap, convert::TryFrom};
+
+use crate::LogsOpts;
+
+const TRUECOLOR_PALETTE: &[(u8, u8, u8)] = &[
+    (0, 255, 0),
+    (128, 128, 0),
+    (0, 255, 255),
+    (255, 192, 203),
+    (245, 120, 250),
+    (221, 160, 221),
+    (154, 205, 50),
+    (230, 230, 120),
+    (255, 165, 0),
+    (135, 206, 250),
+    (50, 205, 50),
+    (186, 85, 211),
+    (64, 224, 208),
+    (255, 215, 0),
+    (100, 149, 237),
+    (255, 127, 80),
+];
+
+const ANSI256_PALETTE: &[u8] = &[
+    46, 142, 51, 218, 213, 183, 148, 186, 214, 117, 77, 134, 80, 220, 69, 209,
+];
+
+/// Red is left out as it is used for errors, and greys for debug lines.
+const ANSI16_PALETTE: &[Color] = &[
+    Color::Green,
+    Color::Cyan,
+    Color::Magenta,
+    Color::Blue,
+    Color::Yellow,
+    Color::DarkGreen,
+    Color::DarkCyan,
+    Color::DarkMagenta,
+    Color::DarkYellow,
+    Color::DarkBlue,
+];
+
+const ANSI16_RGB: &[(Color, (u8, u8, u8))] = &[
+    (Color::Black, (0, 0, 0)),
+    (Color::DarkRed, (128, 0, 0)),
+    (Color::DarkGreen, (0, 128, 0)),
+    (Color::DarkYellow, (128, 128, 0)),
+    (Color::DarkBlue, (0, 0, 128)),
+    (Color::DarkMagenta, (128, 0, 128)),
+    (Color::DarkCyan, (0, 128, 128)),
+    (Color::Grey, (192, 192, 192)),
+    (Color::DarkGrey, (128, 128, 128)),
+    (Color::Red, (255, 0, 0)),
+    (Color::Green, (0, 255, 0)),
+    (Color::Yellow, (255, 255, 0)),
+    (Color::Blue, (0, 0, 255)),
+    (Color::Magenta, (255, 0, 255)),
+    (Color::Cyan, (0, 255, 255)),
+    (Color::White, (255, 255, 255)),
+];
+
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ColorDepth {
+    Ansi16,
+    Ansi256,
+    TrueColor,
+}
+
+impl ColorDepth {
+    pub fn detect() -> Self {
+        let colorterm = std::env::var("COLORTERM").unwrap_or_default();
+        let term = std::env::var("TERM").unwrap_or_default();
+        if colorterm == "truecolor" || colorterm == "24bit" {
+            ColorDepth::TrueColor
+        } else if term.contains("256") {
+            ColorDepth::Ansi256
+        } else {
+            ColorDepth::Ansi16
+        }
+    }
+}
+
+/// Hands out stream colours from a hash of each stream's key, so a pod keeps its colour
+/// between runs. A key whose colour is taken by another active key moves on to the next free
+/// palette entry; colours are only shared once the palette runs out.
+pub struct ColorAssigner {
+    palette: Vec<Color>,
+    by_owner: bool,
+    active: HashMap<String, (usize, usize)>,
+}
+
+impl ColorAssigner {
+    pub fn new(o: &LogsOpts) -> Result<Self> {
+        let depth = ColorDepth::detect();
+        let palette = match &o.palette {
+            Some(p) => parse_palette(p)?
+                .into_iter()
+                .map(|c| adapt(c, depth))
+                .collect(),
+            None => default_palette(depth),
+        };
+
+        Ok(ColorAssigner {
+            palette,
+            by_owner: o.color_by == "owner",
+            active: HashMap::new(),
+        })
+    }
+
+    /// The key a stream's colour is derived from: its prefix, or with `--color-by owner` the
+    /// workload that owns its pod.
+    pub fn key(&self, prefix: &str, owner: Option<&str>) -> String {
+        match owner {
+            Some(o) if self.by_owner => o.to_string(),
+            _ => prefix.to_string(),
+        }
+    }
+
+    pub fn assign(&mut self, key: &str) -> Color {
+        if let Some((index, count)) = self.active.get_mut(key) {
+            *count += 1;
+            return self.palette[*index];
+        }
+
+        let len = self.palette.len();
+        let start = (fnv1a(key) % len as u64) as usize;
+        let taken: Vec<usize> = self.active.values().map(|(i, _)| *i).collect();
+        let index = (0..len)
+            .map(|n| (start + n) % len)
+            .find(|i| !taken.contains(i))
+            .unwrap_or(start);
+        self.active.insert(key.to_string(), (index, 1));
+
+        self.palette[index]
+    }
+
+    pub fn release(&mut self, key: &str) {
+        if let Some((_, count)) = self.active.get_mut(key) {
+            *count -= 1;
+            if *count == 0 {
+                self.active.remove(key);
+            }
+        }
+    }
+}
+
+fn default_palette(depth: ColorDepth) -> Vec<Color> {
+    match depth {
+        ColorDepth::TrueColor => TRUECOLOR_PALETTE
+            .iter()
+            .map(|&(r, g, b)| Color::Rgb { r, g, b })
+            .collect(),
+        ColorDepth::Ansi256 => ANSI256_PALETTE
+            .iter()
+            .map(|&n| Color::AnsiValue(n))
+            .collect(),
+        ColorDepth::Ansi16 => ANSI16_PALETTE.to_vec(),
+    }
+}
+
+/// Parses a comma separated list of `#rrggbb` values, 256-colour indexes and colour names.
+fn parse_palette(p: &str) -> Result<Vec<Color>> {
+    let colors = p
+        .split(',')
+        .map(str::trim)
+        .filter(|c| !c.is_empty())
+        .map(|c| {
+            if let Some(hex) = c.strip_prefix('#') {
+                let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2).unwrap_or(""), 16);
+                match (hex.len(), channel(0), channel(2), channel(4)) {
+                    (6, Ok(r), Ok(g), Ok(b)) => Ok(Color::Rgb { r, g, b }),
+                    _ => Err(anyhow!("invalid colour '{}'", c)),
+                }
+            } else if let Ok(n) = c.parse::<u8>() {
+                Ok(Color::AnsiValue(n))
+            } else {
+                Color::try_from(c).map_err(|_| anyhow!("invalid colour '{}'", c))
+            }
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    if colors.is_empty() {
+        return Err(anyhow!("--palette needs at least one colour"));
+    }
+
+    Ok(colors)
+}
+
+/// Downgrades a colour to what the terminal can show.
+fn adapt(c: Color, depth: ColorDepth) -> Color {
+    match (depth, c) {
+        (ColorDepth::TrueColor, _) => c,
+        (ColorDepth::Ansi256, Color::Rgb { r, g, b }) => {
+            let level = |v: u8| (v as u16 * 5 + 127) / 255;
+            Color::AnsiValue((16 + 36 * level(r) + 6 * level(g) + level(b)) as u8)
+        }
+        (ColorDepth::Ansi256, _) => c,
+        (ColorDepth::Ansi16, Color::Rgb { r, g, b }) => nearest_ansi16((r, g, b)),
+        (ColorDepth::Ansi16, Color::AnsiValue(n)) => nearest_ansi16(ansi256_rgb(n)),
+        (ColorDepth::Ansi16, _) => c,
+    }
+}
+
+fn ansi256_rgb(n: u8) -> (u8, u8, u8) {
+    match n {
+        0..=15 => ANSI16_RGB[n as usize].1,
+        16..=231 => {
+            let i = (n - 16) as usize;
+            (
+                CUBE_LEVELS[i / 36],
+                CUBE_LEVELS[(i / 6) % 6],
+                CUBE_LEVELS[i % 6],
+            )
+        }
+        _ => {
+            let v = 8 + 10 * (n - 232);
+            (v, v, v)
+        }
+    }
+}
+
+fn nearest_ansi16((r, g, b): (u8, u8, u8)) -> Color {
+    let distance = |(cr, cg, cb): (u8, u8, u8)| {
+        let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2);
+        d(r, cr) + d(g, cg) + d(b, cb)
+    };
+
+    ANSI16_RGB
+        .iter()
+        .min_by_key(|(_, rgb)| distance(*rgb))
+        .map(|(c, _)| *c)
+        .unwrap_or(Color::White)
+}
+
+/// FNV-1a, used instead of `DefaultHasher` whose output may change between Rust releases.
+fn fnv1a(s: &str) -> u64 {
+    s.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, b| {
+        (hash ^ b as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
@@ -0,0 +1,295 @@
+//! Defaults loaded from `~/.config/ice-kube/config.toml` (or `$XDG_CONFIG_HOME/ice-kube`).
+//!
+//! ```toml
+//! namespace = "default"
+//! tail_length = 200
+//! highlight = "timeout|refused"
+//!
+//! [keys]
+//! describe = "i"
+//!
+//! [profiles.prod]
+//! context = "prod-cluster"
+//! namespace = "payments"
+//! ```
+//!
+//! Flags given on the command line always win, then the selected `--profile`, then the top
+//! level of the file, and finally the built in defaults.
+
+use anyhow::{anyhow, Context, Result};
+use clap::ArgMatches;
+use crossterm::event::KeyCode;
+use serde::Deserialize;
+use std::{collections::HashMap, path::PathBuf};
+
+use crate::{ui::is_tab_key, ClusterOpts, LogsOpts, ScaleOpts, UIOpts};
+
+/// The ui actions that can be rebound under `[keys]`, with their default keys.
+const KEY_ACTIONS: &[(&str, char)] = &[
+    ("quit", 'q'),
+    ("down", 'j'),
+    ("up", 'k'),
+    ("end", 'G'),
+    ("logs", 'l'),
+    ("describe", 'd'),
+    ("crash_report", 'x'),
+    ("pause", 'p'),
+    ("container", 'c'),
+    ("previous", 'P'),
+    ("filter", '/'),
+    ("wrap", 'w'),
+    ("command", ':'),
+    ("sort_name", 'n'),
+    ("sort_age", 'a'),
+    ("sort_restarts", 'r'),
+    ("sort_status", 's'),
+    ("delete", 'K'),
+    ("force_delete", 'F'),
+    ("restart", 'O'),
+    ("scale", 'z'),
+];
+
+/// The actions only matched in the log pane, where tab keys are free to be rebound to.
+const LOG_PANE_ACTIONS: &[&str] = &["end", "pause", "container", "previous", "wrap"];
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    namespace: Option<String>,
+    context: Option<String>,
+    kubeconfig: Option<String>,
+    request_timeout: Option<u64>,
+    tail_length: Option<i64>,
+    highlight: Option<String>,
+    palette: Option<String>,
+    color_by: Option<String>,
+    keys: HashMap<String, String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    #[serde(flatten)]
+    defaults: Settings,
+    profiles: HashMap<String, Settings>,
+}
+
+impl Config {
+    pub fn load() -> Result<Self> {
+        let path = match config_path() {
+            Some(p) if p.exists() => p,
+            _ => return Ok(Config::default()),
+        };
+
+        let text = std::fs::read_to_string(&path)
+            .with_context(|| format!("reading {}", path.display()))?;
+        toml::from_str(&text).with_context(|| format!("parsing {}", path.display()))
+    }
+
+    /// The top level settings with those of `profile` layered on top.
+    pub fn settings(&self, profile: Option<&str>) -> Result<Settings> {
+        let name = match profile {
+            Some(p) => p,
+            None => return Ok(self.defaults.clone()),
+        };
+        let p = self
+            .profiles
+            .get(name)
+            .ok_or_else(|| anyhow!("no profile named '{}' in the config file", name))?;
+
+        let mut keys = self.defaults.keys.clone();
+        keys.extend(p.keys.clone());
+        let d = &self.defaults;
+
+        Ok(Settings {
+            namespace: p.namespace.clone().or_else(|| d.namespace.clone()),
+            context: p.context.clone().or_else(|| d.context.clone()),
+            kubeconfig: p.kubeconfig.clone().or_else(|| d.kubeconfig.clone()),
+            request_timeout: p.request_timeout.or(d.request_timeout),
+            tail_length: p.tail_length.or(d.tail_length),
+            highlight: p.highlight.clone().or_else(|| d.highlight.clone()),
+            palette: p.palette.clone().or_else(|| d.palette.clone()),
+            color_by: p.color_by.clone().or_else(|| d.color_by.clone()),
+            keys,
+        })
+    }
+}
+
+impl Settings {
+    fn apply_cluster(&self, o: &mut ClusterOpts, m: &ArgMatches) {
+        o.context = o.context.take().or_else(|| self.context.clone());
+        o.kubeconfig = o.kubeconfig.take().or_else(|| self.kubeconfig.clone());
+        if let (false, Some(t)) = (given(m, "request-timeout"), self.request_timeout) {
+            o.request_timeout = t;
+        }
+    }
+
+    pub fn apply_logs(&self, o: &mut LogsOpts, m: &ArgMatches) -> Result<()> {
+        self.apply_cluster(&mut o.cluster, m);
+        if let (false, Some(n)) = (given(m, "namespace"), &self.namespace) {
+            o.namespace = n.clone();
+        }
+        if let (false, Some(t)) = (given(m, "tail-length"), self.tail_length) {
+            o.tail_length = t;
+        }
+        if let (false, Some(c)) = (given(m, "color-by"), &self.color_by) {
+            if c != "pod" && c != "owner" {
+                return Err(anyhow!("color_by must be 'pod' or 'owner', not '{}'", c));
+            }
+            o.color_by = c.clone();
+        }
+        o.highlight = o.highlight.take().or_else(|| self.highlight.clone());
+        o.palette = o.palette.take().or_else(|| self.palette.clone());
+
+        Ok(())
+    }
+
+    pub fn apply_ui(&self, o: &mut UIOpts, m: &ArgMatches) -> Result<()> {
+        self.apply_cluster(&mut o.cluster, m);
+        if let (false, Some(n)) = (given(m, "namespace"), &self.namespace) {
+            o.namespace = n.clone();
+        }
+        if let (false, Some(t)) = (given(m, "tail-length"), self.tail_length) {
+            o.tail_length = t;
+        }
+        o.highlight = o.highlight.take().or_else(|| self.highlight.clone());
+        o.keys = KeyMap::new(&self.keys)?;
+
+        Ok(())
+    }
+
+    pub fn apply_scale(&self, o: &mut ScaleOpts, m: &ArgMatches) {
+        self.apply_cluster(&mut o.cluster, m);
+        if let (false, Some(n)) = (given(m, "namespace"), &self.namespace) {
+            o.namespace = n.clone();
+        }
+    }
+}
+
+fn given(m: &ArgMatches, id: &str) -> bool {
+    m.occurrences_of(id) > 0
+}
+
+fn config_path() -> Option<PathBuf> {
+    let base = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(d) => PathBuf::from(d),
+        None => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+    };
+
+    Some(base.join("ice-kube").join("config.toml"))
+}
+
+/// Translates rebound keys back to the defaults the ui matches on. A rebound action no longer
+/// answers to its default key, unless another action was rebound to it.
+#[derive(Clone, Debug, Default)]
+pub struct KeyMap {
+    remap: HashMap<char, Option<char>>,
+}
+
+impl KeyMap {
+    pub fn new(keys: &HashMap<String, String>) -> Result<Self> {
+        let mut bound = HashMap::new();
+        for (action, key) in keys {
+            if !KEY_ACTIONS.iter().any(|(a, _)| a == action) {
+                return Err(anyhow!("unknown key action '{}'", action));
+            }
+            let mut chars = key.chars();
+            let key = match (chars.next(), chars.next()) {
+                (Some(c), None) => c,
+                _ => return Err(anyhow!("key for '{}' must be one character", action)),
+            };
+            if is_tab_key(key) && !LOG_PANE_ACTIONS.contains(&action.as_str()) {
+                return Err(anyhow!(
+                    "key '{}' for '{}' is used to switch tabs",
+                    key,
+                    action
+                ));
+            }
+            bound.insert(action.as_str(), key);
+        }
+
+        let mut remap = HashMap::new();
+        let mut owners = HashMap::new();
+        for (action, default) in KEY_ACTIONS {
+            let key = bound.get(action).copied().unwrap_or(*default);
+            if let Some(other) = owners.insert(key, action) {
+                return Err(anyhow!(
+                    "key '{}' is bound to both '{}' and '{}'",
+                    key,
+                    other,
+                    action
+                ));
+            }
+            if key != *default {
+                remap.insert(key, Some(*default));
+            }
+        }
+        for (action, default) in KEY_ACTIONS {
+            if bound.contains_key(action) {
+                remap.entry(*default).or_insert(None);
+            }
+        }
+
+        Ok(KeyMap { remap })
+    }
+
+    pub fn translate(&self, code: KeyCode) -> KeyCode {
+        match code {
+            KeyCode::Char(c) => match self.remap.get(&c) {
+                Some(Some(d)) => KeyCode::Char(*d),
+                Some(None) => KeyCode::Null,
+                None => code,
+            },
+            _ => code,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keymap(keys: &[(&str, &str)]) -> Result<KeyMap> {
+        let keys = keys
+            .iter()
+            .map(|(a, k)| (a.to_string(), k.to_string()))
+            .collect();
+        KeyMap::new(&keys)
+    }
+
+    #[test]
+    fn rebinding_suppresses_only_the_default_key() {
+        let keys = keymap(&[("describe", "i")]).unwrap();
+        assert_eq!(keys.translate(KeyCode::Char('i')), KeyCode::Char('d'));
+        assert_eq!(keys.translate(KeyCode::Char('d')), KeyCode::Null);
+        assert_eq!(keys.translate(KeyCode::Char('l')), KeyCode::Char('l'));
+        assert_eq!(keys.translate(KeyCode::Enter), KeyCode::Enter);
+    }
+
+    #[test]
+    fn actions_can_swap_keys() {
+        let keys = keymap(&[("describe", "l"), ("logs", "d")]).unwrap();
+        assert_eq!(keys.translate(KeyCode::Char('l')), KeyCode::Char('d'));
+        assert_eq!(keys.translate(KeyCode::Char('d')), KeyCode::Char('l'));
+    }
+
+    #[test]
+    fn log_pane_actions_can_use_tab_keys() {
+        assert!(keymap(&[("previous", "P")]).is_ok());
+        let keys = keymap(&[("container", "C"), ("wrap", "3")]).unwrap();
+        assert_eq!(keys.translate(KeyCode::Char('C')), KeyCode::Char('c'));
+        assert_eq!(keys.translate(KeyCode::Char('3')), KeyCode::Char('w'));
+        assert_eq!(keys.translate(KeyCode::Char('c')), KeyCode::Null);
+    }
+
+    #[test]
+    fn rejects_conflicting_keys() {
+        assert!(keymap(&[("describe", "D")]).is_err());
+        assert!(keymap(&[("describe", "3")]).is_err());
+        assert!(keymap(&[("describe", "l")]).is_err());
+        assert!(keymap(&[("describe", "i"), ("logs", "i")]).is_err());
+        assert!(keymap(&[("describe", "di")]).is_err());
+        assert!(keymap(&[("sort_name", "N")]).is_err());
+        assert!(keymap(&[("explain", "i")]).is_err());
+    }
+}
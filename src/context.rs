@@ -0,0 +1,129 @@
+use anyhow::Result;
+use k8s_openapi::{
+    api::core::v1::{Namespace, Pod},
+    Resource,
+};
+use kube::{
+    api::{ListParams, Meta},
+    config::{KubeConfigOptions, Kubeconfig},
+    Api, Client, Config,
+};
+use std::{future::Future, time::Duration};
+
+use crate::ClusterOpts;
+
+#[derive(Clone)]
+pub struct KubeContext {
+    client: Client,
+    name: String,
+    timeout: Option<Duration>,
+}
+
+impl KubeContext {
+    pub async fn new(opts: &ClusterOpts) -> Result<Self> {
+        Self::connect(opts, opts.context.as_deref()).await
+    }
+
+    /// Connects to `context` from the kubeconfig, or to the current one when it is `None`.
+    pub async fn connect(opts: &ClusterOpts, context: Option<&str>) -> Result<Self> {
+        let explicit = context.is_some() || opts.kubeconfig.is_some();
+        let (mut client_config, name) = if explicit {
+            let kubeconfig = read_kubeconfig(opts)?;
+            let name = context
+                .map(String::from)
+                .unwrap_or_else(|| kubeconfig.current_context.clone());
+            let options = KubeConfigOptions {
+                context: Some(name.clone()),
+                ..KubeConfigOptions::default()
+            };
+
+            (
+                Config::from_custom_kubeconfig(kubeconfig, &options).await?,
+                name,
+            )
+        } else {
+            let name = Kubeconfig::read()
+                .map(|c| c.current_context)
+                .unwrap_or_else(|_| String::from("in-cluster"));
+            let c = match Config::infer().await {
+                Ok(c) => c,
+                Err(_) => Config::from_kubeconfig(&KubeConfigOptions::default()).await?,
+            };
+
+            (c, name)
+        };
+
+        // Log streams stay open indefinitely, so the client itself never times out;
+        // one-shot requests are bounded through `request` instead.
+        client_config.timeout = None;
+
+        let timeout = match opts.request_timeout {
+            0 => None,
+            secs => Some(Duration::from_secs(secs)),
+        };
+
+        Ok(KubeContext {
+            client: Client::new(client_config),
+            name,
+            timeout,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn client(&self) -> Client {
+        self.client.clone()
+    }
+
+    pub fn pods(&self, namespace: &str) -> Api<Pod> {
+        Api::namespaced(self.client(), namespace)
+    }
+
+    /// Pods in `namespace`, or in every namespace when it is `None`.
+    pub fn pods_in(&self, namespace: Option<&str>) -> Api<Pod> {
+        self.api_in(namespace)
+    }
+
+    /// Objects of any kind in `namespace`, or in every namespace when it is `None`.
+    pub fn api_in<K: Resource>(&self, namespace: Option<&str>) -> Api<K> {
+        match namespace {
+            Some(n) => Api::namespaced(self.client(), n),
+            None => Api::all(self.client()),
+        }
+    }
+
+    pub async fn namespaces(&self) -> Result<Vec<String>> {
+        let api: Api<Namespace> = Api::all(self.client());
+        let list = self.request(api.list(&ListParams::default())).await?;
+
+        Ok(list.items.iter().map(Meta::name).collect())
+    }
+
+    pub async fn request<F, T>(&self, fut: F) -> Result<T>
+    where
+        F: Future<Output = kube::Result<T>>,
+    {
+        match self.timeout {
+            Some(t) => Ok(tokio::time::timeout(t, fut).await??),
+            None => Ok(fut.await?),
+        }
+    }
+}
+
+/// The names of the contexts in the kubeconfig.
+pub fn contexts(opts: &ClusterOpts) -> Result<Vec<String>> {
+    let kubeconfig = read_kubeconfig(opts)?;
+
+    Ok(kubeconfig.contexts.into_iter().map(|c| c.name).collect())
+}
+
+fn read_kubeconfig(opts: &ClusterOpts) -> Result<Kubeconfig> {
+    let kubeconfig = match &opts.kubeconfig {
+        Some(path) => Kubeconfig::read_from(path)?,
+        None => Kubeconfig::read()?,
+    };
+
+    Ok(kubeconfig)
+}
@@ -0,0 +1,414 @@
+use anyhow::Result;
+use k8s_openapi::api::core::v1::{ContainerState, EnvVar, Event, Pod};
+use std::{collections::BTreeMap, fmt::Write};
+
+use crate::util::format_age;
+
+const INDENT: usize = 16;
+
+pub fn describe_pod(pod: &Pod, events: &[Event]) -> Result<String> {
+    let mut out = String::new();
+    let meta = &pod.metadata;
+    let spec = pod.spec.as_ref();
+    let status = pod.status.as_ref();
+
+    field(&mut out, 0, "Name", meta.name.as_deref().unwrap_or(""))?;
+    field(
+        &mut out,
+        0,
+        "Namespace",
+        meta.namespace.as_deref().unwrap_or(""),
+    )?;
+    field(
+        &mut out,
+        0,
+        "Node",
+        spec.and_then(|s| s.node_name.as_deref())
+            .unwrap_or("<none>"),
+    )?;
+    if let Some(t) = status.and_then(|s| s.start_time.as_ref()) {
+        field(&mut out, 0, "Start Time", &t.0.to_rfc2822())?;
+    }
+    map_field(&mut out, "Labels", meta.labels.as_ref())?;
+    map_field(&mut out, "Annotations", meta.annotations.as_ref())?;
+    field(
+        &mut out,
+        0,
+        "Status",
+        status.and_then(|s| s.phase.as_deref()).unwrap_or(""),
+    )?;
+    field(
+        &mut out,
+        0,
+        "IP",
+        status.and_then(|s| s.pod_ip.as_deref()).unwrap_or(""),
+    )?;
+    if let Some(owners) = &meta.owner_references {
+        for o in owners {
+            field(
+                &mut out,
+                0,
+                "Controlled By",
+                &format!("{}/{}", o.kind, o.name),
+            )?;
+        }
+    }
+
+    writeln!(out, "Containers:")?;
+    let empty = vec![];
+    let statuses = status
+        .and_then(|s| s.container_statuses.as_ref())
+        .unwrap_or(&empty);
+    for c in spec.map(|s| s.containers.as_slice()).unwrap_or(&[]) {
+        writeln!(out, "  {}:", c.name)?;
+        field(&mut out, 4, "Image", c.image.as_deref().unwrap_or(""))?;
+        if let Some(ports) = &c.ports {
+            let ports: Vec<String> = ports
+                .iter()
+                .map(|p| {
+                    format!(
+                        "{}/{}",
+                        p.container_port,
+                        p.protocol.as_deref().unwrap_or("TCP")
+                    )
+                })
+                .collect();
+            field(&mut out, 4, "Ports", &ports.join(", "))?;
+        }
+
+        if let Some(cs) = statuses.iter().find(|s| s.name == c.name) {
+            container_state(&mut out, "State", cs.state.as_ref())?;
+            if cs
+                .last_state
+                .as_ref()
+                .map_or(false, |s| s.terminated.is_some())
+            {
+                container_state(&mut out, "Last State", cs.last_state.as_ref())?;
+            }
+            field(&mut out, 4, "Ready", &cs.ready.to_string())?;
+            field(&mut out, 4, "Restart Count", &cs.restart_count.to_string())?;
+        }
+
+        match &c.env {
+            Some(env) if !env.is_empty() => {
+                writeln!(out, "    Environment:")?;
+                for e in env {
+                    writeln!(out, "      {}:  {}", e.name, env_value(e))?;
+                }
+            }
+            _ => field(&mut out, 4, "Environment", "<none>")?,
+        }
+
+        match &c.volume_mounts {
+            Some(mounts) if !mounts.is_empty() => {
+                writeln!(out, "    Mounts:")?;
+                for m in mounts {
+                    let mode = if m.read_only.unwrap_or(false) {
+                        "ro"
+                    } else {
+                        "rw"
+                    };
+                    writeln!(out, "      {} from {} ({})", m.mount_path, m.name, mode)?;
+                }
+            }
+            _ => field(&mut out, 4, "Mounts", "<none>")?,
+        }
+    }
+
+    writeln!(out, "Conditions:")?;
+    writeln!(out, "  Type              Status")?;
+    if let Some(conditions) = status.and_then(|s| s.conditions.as_ref()) {
+        for c in conditions {
+            writeln!(out, "  {:<18}{}", c.type_, c.status)?;
+        }
+    }
+
+    writeln!(out, "Events:")?;
+    if events.is_empty() {
+        writeln!(out, "  <none>")?;
+    } else {
+        writeln!(
+            out,
+            "  Type      Reason                  Age     From                Message"
+        )?;
+        for e in events {
+            let age = e
+                .last_timestamp
+                .as_ref()
+                .or_else(|| e.first_timestamp.as_ref())
+                .map(|t| format_age(&t.0))
+                .unwrap_or_else(|| String::from("<unknown>"));
+            let from = e
+                .source
+                .as_ref()
+                .and_then(|s| s.component.as_deref())
+                .unwrap_or("");
+            writeln!(
+                out,
+                "  {:<10}{:<24}{:<8}{:<20}{}",
+                e.type_.as_deref().unwrap_or(""),
+                e.reason.as_deref().unwrap_or(""),
+                age,
+                from,
+                e.message.as_deref().unwrap_or("").trim()
+            )?;
+        }
+    }
+
+    Ok(out)
+}
+
+/// The status `kubectl get pods` shows: the reason a container is waiting or terminated
+/// (CrashLoopBackOff, ImagePullBackOff, Completed, ...) in preference to the pod's phase.
+pub fn pod_status(pod: &Pod) -> String {
+    let status = pod.status.as_ref();
+    let mut reason = status
+        .and_then(|s| s.reason.clone().or_else(|| s.phase.clone()))
+        .unwrap_or_default();
+
+    let empty = vec![];
+    let init = status
+        .and_then(|s| s.init_container_statuses.as_ref())
+        .unwrap_or(&empty);
+    let mut initializing = false;
+    for (i, c) in init.iter().enumerate() {
+        let state = c.state.as_ref();
+        match (
+            state.and_then(|s| s.terminated.as_ref()),
+            state.and_then(|s| s.waiting.as_ref()),
+        ) {
+            (Some(t), _) if t.exit_code == 0 => continue,
+            (Some(t), _) => {
+                reason = match (&t.reason, t.signal) {
+                    (Some(r), _) => format!("Init:{}", r),
+                    (None, Some(s)) => format!("Init:Signal:{}", s),
+                    (None, None) => format!("Init:ExitCode:{}", t.exit_code),
+                };
+            }
+            (None, Some(w))
+                if w.reason
+                    .as_deref()
+                    .map_or(false, |r| r != "PodInitializing") =>
+            {
+                reason = format!("Init:{}", w.reason.as_deref().unwrap_or(""));
+            }
+            _ => reason = format!("Init:{}/{}", i, init.len()),
+        }
+        initializing = true;
+        break;
+    }
+
+    if !initializing {
+        let statuses = status
+            .and_then(|s| s.container_statuses.as_ref())
+            .unwrap_or(&empty);
+        let mut running = false;
+        for c in statuses.iter().rev() {
+            let state = match &c.state {
+                Some(s) => s,
+                None => continue,
+            };
+            if let Some(r) = state.waiting.as_ref().and_then(|w| w.reason.as_ref()) {
+                reason = r.clone();
+            } else if let Some(t) = &state.terminated {
+                reason = match (&t.reason, t.signal) {
+                    (Some(r), _) => r.clone(),
+                    (None, Some(s)) => format!("Signal:{}", s),
+                    (None, None) => format!("ExitCode:{}", t.exit_code),
+                };
+            } else if state.running.is_some() && c.ready {
+                running = true;
+            }
+        }
+
+        // A finished sidecar leaves "Completed" behind while the rest of the pod still runs.
+        if reason == "Completed" && running {
+            reason = String::from("Running");
+        }
+    }
+
+    if pod.metadata.deletion_timestamp.is_some() {
+        if status.and_then(|s| s.reason.as_deref()) == Some("NodeLost") {
+            reason = String::from("Unknown");
+        } else {
+            reason = String::from("Terminating");
+        }
+    }
+
+    reason
+}
+
+/// The kind and name of the controller of `pod`, if it has one.
+pub fn pod_controller(pod: &Pod) -> Option<(String, String)> {
+    let owners = pod.metadata.owner_references.as_ref()?;
+    owners
+        .iter()
+        .find(|o| o.controller == Some(true))
+        .or_else(|| owners.first())
+        .map(|o| (o.kind.clone(), o.name.clone()))
+}
+
+/// Containers whose previous instance terminated, i.e. the ones with a crash to report.
+pub fn crashed_containers(pod: &Pod) -> Vec<String> {
+    pod.status
+        .as_ref()
+        .and_then(|s| s.container_statuses.as_ref())
+        .map(|cs| {
+            cs.iter()
+                .filter(|c| {
+                    c.last_state
+                        .as_ref()
+                        .map_or(false, |s| s.terminated.is_some())
+                })
+                .map(|c| c.name.clone())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Combines the last terminated state of each crashed container with the tail of its
+/// previous logs, given as `(container, logs)` pairs.
+pub fn crash_report(pod: &Pod, logs: &[(String, String)]) -> Result<String> {
+    let mut out = String::new();
+    field(
+        &mut out,
+        0,
+        "Name",
+        pod.metadata.name.as_deref().unwrap_or(""),
+    )?;
+    if logs.is_empty() {
+        writeln!(out, "No container has terminated since the pod started.")?;
+        return Ok(out);
+    }
+
+    let empty = vec![];
+    let statuses = pod
+        .status
+        .as_ref()
+        .and_then(|s| s.container_statuses.as_ref())
+        .unwrap_or(&empty);
+    for (name, text) in logs {
+        writeln!(out)?;
+        writeln!(out, "Container {}:", name)?;
+        if let Some(cs) = statuses.iter().find(|s| s.name == *name) {
+            field(&mut out, 4, "Restart Count", &cs.restart_count.to_string())?;
+            container_state(&mut out, "State", cs.state.as_ref())?;
+            container_state(&mut out, "Last State", cs.last_state.as_ref())?;
+            if let Some(t) = cs.last_state.as_ref().and_then(|s| s.terminated.as_ref()) {
+                if let Some(m) = &t.message {
+                    field(&mut out, 6, "Message", m.trim())?;
+                }
+            }
+        }
+
+        writeln!(out, "    Previous Logs:")?;
+        if text.trim().is_empty() {
+            writeln!(out, "      <none>")?;
+        }
+        for line in text.lines() {
+            writeln!(out, "      {}", line)?;
+        }
+    }
+
+    Ok(out)
+}
+
+fn field(out: &mut String, indent: usize, name: &str, value: &str) -> Result<()> {
+    let label = format!("{}:", name);
+    writeln!(
+        out,
+        "{:indent$}{:<width$}{}",
+        "",
+        label,
+        value,
+        indent = indent,
+        width = INDENT
+    )?;
+
+    Ok(())
+}
+
+fn map_field(out: &mut String, name: &str, map: Option<&BTreeMap<String, String>>) -> Result<()> {
+    match map {
+        Some(m) if !m.is_empty() => {
+            for (i, (k, v)) in m.iter().enumerate() {
+                if i == 0 {
+                    field(out, 0, name, &format!("{}={}", k, v))?;
+                } else {
+                    writeln!(out, "{:width$}{}={}", "", k, v, width = INDENT)?;
+                }
+            }
+        }
+        _ => field(out, 0, name, "<none>")?,
+    }
+
+    Ok(())
+}
+
+fn container_state(out: &mut String, name: &str, state: Option<&ContainerState>) -> Result<()> {
+    let state = match state {
+        Some(s) => s,
+        None => return Ok(()),
+    };
+
+    if let Some(r) = &state.running {
+        field(out, 4, name, "Running")?;
+        if let Some(t) = &r.started_at {
+            field(out, 6, "Started", &t.0.to_rfc2822())?;
+        }
+    } else if let Some(w) = &state.waiting {
+        field(out, 4, name, "Waiting")?;
+        if let Some(reason) = &w.reason {
+            field(out, 6, "Reason", reason)?;
+        }
+    } else if let Some(t) = &state.terminated {
+        field(out, 4, name, "Terminated")?;
+        if let Some(reason) = &t.reason {
+            field(out, 6, "Reason", reason)?;
+        }
+        field(out, 6, "Exit Code", &t.exit_code.to_string())?;
+        if let Some(signal) = t.signal {
+            field(out, 6, "Signal", &signal.to_string())?;
+        }
+        if let Some(f) = &t.finished_at {
+            field(out, 6, "Finished", &f.0.to_rfc2822())?;
+        }
+    }
+
+    Ok(())
+}
+
+fn env_value(e: &EnvVar) -> String {
+    if let Some(v) = &e.value {
+        return v.clone();
+    }
+
+    let source = match &e.value_from {
+        Some(s) => s,
+        None => return String::new(),
+    };
+
+    if let Some(s) = &source.secret_key_ref {
+        format!(
+            "<set to the key '{}' in secret '{}'>",
+            s.key,
+            s.name.as_deref().unwrap_or("")
+        )
+    } else if let Some(c) = &source.config_map_key_ref {
+        format!(
+            "<set to the key '{}' of config map '{}'>",
+            c.key,
+            c.name.as_deref().unwrap_or("")
+        )
+    } else if let Some(f) = &source.field_ref {
+        format!("({})", f.field_path)
+    } else if let Some(r) = &source.resource_field_ref {
+        format!(
+            "{} ({})",
+            r.resource,
+            r.container_name.as_deref().unwrap_or("")
+        )
+    } else {
+        String::new()
+    }
+}
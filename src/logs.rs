@@ -1,128 +1,447 @@
 use futures::{StreamExt, TryStreamExt};
-use std::{collections::HashMap, io};
-
-use anyhow::Result;
-use crossterm::{
-    event, execute,
-    style::{
-        Attribute, Color, Print, ResetColor, SetAttribute, SetBackgroundColor, SetForegroundColor,
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    io,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
     },
-    Result as CrossResult,
 };
-use futures::future::join_all;
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use crossterm::style::Color;
+use futures::future::{abortable, join_all, AbortHandle};
 use k8s_openapi::api::core::v1::Pod;
 use kube::{
     api::{ListParams, LogParams, Meta},
-    config::KubeConfigOptions,
-    Api, Client, Config,
+    Api,
 };
-use lazy_static::lazy_static;
-use log::{debug, error, info, log_enabled, Level};
+use log::{debug, error, info};
 use regex::Regex;
-use std::io::{stdout, Write};
+use std::{io::Write, time::Duration};
 
-use tokio::task;
+use tokio::{
+    sync::mpsc,
+    task,
+    time::{delay_for, timeout_at, Instant},
+};
 
 use crate::{
-    util::{get_pods, OptionEx},
-    LogsOpts,
+    color::ColorAssigner,
+    context::KubeContext,
+    describe::crashed_containers,
+    output::Printer,
+    query::Query,
+    save::LogSaver,
+    structured::{JsonFormat, Level},
+    util::{get_pods, parse_ago, parse_time, pod_list_params, qualified_name, OptionEx},
+    watch::{watch_pods, PodEvent},
+    JsonOpts, LogsOpts,
 };
 
-lazy_static! {
-    static ref COLORS: Vec<Color> = vec![
-        Color::Rgb { r: 0, g: 255, b: 0 },
-        Color::Rgb {
-            r: 128,
-            g: 128,
-            b: 0
-        },
-        Color::Rgb {
-            r: 0,
-            g: 255,
-            b: 255
-        },
-        Color::Rgb {
-            r: 255,
-            g: 192,
-            b: 203
-        },
-        Color::Rgb {
-            r: 245,
-            g: 120,
-            b: 250
-        },
-        Color::Rgb {
-            r: 221,
-            g: 160,
-            b: 221
-        },
-        Color::Rgb {
-            r: 154,
-            g: 205,
-            b: 50
-        },
-        Color::Rgb {
-            r: 230,
-            g: 230,
-            b: 120
-        },
-    ];
-}
-
-pub fn get_color() -> Result<Color> {
-    use rand::Rng;
-
-    let mut rng = rand::thread_rng();
-    let index = rng.gen_range(0.0, COLORS.len() as f32) as usize;
-    let rgb = COLORS[index];
-
-    Ok(rgb)
-}
-
-pub async fn follow_logs(o: &LogsOpts, p: &str) -> Result<()> {
-    let pods = collect_pods(&o.namespace, &p).await?;
-    println!("Pods: {:?}", pods);
-    let mut tasks = vec![];
-    for name in pods {
-        let c = get_color()?;
-        let n = o.namespace.clone();
-        let pn = name.clone();
+const RECONNECT_BACKOFF_MIN: Duration = Duration::from_secs(1);
+const RECONNECT_BACKOFF_MAX: Duration = Duration::from_secs(30);
+
+#[derive(Clone, Debug)]
+pub struct LogTarget {
+    pub namespace: String,
+    pub pod: String,
+    pub container: Option<String>,
+    pub owner: Option<String>,
+    pub prefix: String,
+}
 
-        let h = o.highlight.to_str();
+impl LogTarget {
+    pub fn new(namespace: &str, pod: &Pod, container: Option<&str>, show_container: bool) -> Self {
+        let name = Meta::name(pod);
+        let prefix = match container {
+            Some(c) if show_container => format!("{}/{}", name, c),
+            _ => name.clone(),
+        };
+
+        LogTarget {
+            namespace: namespace.to_string(),
+            pod: name,
+            container: container.map(String::from),
+            owner: pod_owner(pod),
+            prefix,
+        }
+    }
+}
+
+/// The workload that owns `pod`. The pod-template hash is stripped from ReplicaSet names so
+/// every pod of a Deployment reports the same owner.
+pub fn pod_owner(pod: &Pod) -> Option<String> {
+    let owner = pod
+        .metadata
+        .owner_references
+        .as_ref()?
+        .iter()
+        .find(|o| o.controller == Some(true))?;
+    let hash = pod
+        .metadata
+        .labels
+        .as_ref()
+        .and_then(|l| l.get("pod-template-hash"));
+
+    match hash {
+        Some(h) if owner.kind == "ReplicaSet" => {
+            let suffix = format!("-{}", h);
+            Some(
+                owner
+                    .name
+                    .strip_suffix(suffix.as_str())
+                    .unwrap_or(&owner.name)
+                    .to_string(),
+            )
+        }
+        _ => Some(owner.name.clone()),
+    }
+}
+
+pub fn pod_containers(pod: &Pod) -> Vec<String> {
+    pod.spec
+        .as_ref()
+        .map(|s| s.containers.iter().map(|c| c.name.clone()).collect())
+        .unwrap_or_default()
+}
+
+/// Picks the containers of `pod` to stream according to `--container` and `--all-containers`.
+/// Pods with a single container keep the plain pod name as their prefix.
+pub fn log_targets(pod: &Pod, o: &LogsOpts) -> Result<Vec<LogTarget>> {
+    let namespace = Meta::namespace(pod).unwrap_or_else(|| o.namespace.clone());
+    let containers = pod_containers(pod);
+
+    let selected: Vec<&String> = if o.all_containers {
+        containers.iter().collect()
+    } else if let Some(c) = &o.container {
+        let re = Regex::new(c)?;
+        containers.iter().filter(|c| re.is_match(c)).collect()
+    } else {
+        containers.iter().take(1).collect()
+    };
+
+    let show_container = o.all_containers || o.container.is_some() || containers.len() > 1;
+    Ok(selected
+        .into_iter()
+        .map(|c| LogTarget::new(&namespace, pod, Some(c.as_str()), show_container))
+        .map(|t| qualify_target(t, o))
+        .collect())
+}
+
+/// Prefixes the target with its namespace when pods come from all namespaces.
+fn qualify_target(mut target: LogTarget, o: &LogsOpts) -> LogTarget {
+    if o.all_namespaces {
+        target.prefix = format!("{}/{}", target.namespace, target.prefix);
+    }
+
+    target
+}
+
+/// The namespace pods are listed in, or `None` with `--all-namespaces`.
+fn pod_scope(o: &LogsOpts) -> Option<&str> {
+    if o.all_namespaces {
+        None
+    } else {
+        Some(&o.namespace)
+    }
+}
+
+/// The name patterns are matched against: `namespace/pod` with `--all-namespaces`.
+fn display_name(pod: &Pod, o: &LogsOpts) -> String {
+    if o.all_namespaces {
+        qualified_name(pod)
+    } else {
+        Meta::name(pod)
+    }
+}
+
+pub async fn pod_logs(ctx: &KubeContext, o: &LogsOpts, pod_name: &str) -> Result<()> {
+    let pods = if o.all_namespaces {
+        let lp = ListParams::default().fields(&format!("metadata.name={}", pod_name));
+        get_pods(ctx, None, &lp).await?
+    } else {
+        vec![ctx.request(ctx.pods(&o.namespace).get(pod_name)).await?]
+    };
+    if pods.is_empty() {
+        return Err(anyhow::anyhow!(
+            "No pod named {} in any namespace",
+            pod_name
+        ));
+    }
 
-        let t = task::spawn(stream_logs(n, pn, o.tail_length, c, h, o.filter));
+    let mut targets = vec![];
+    for pod in &pods {
+        targets.extend(log_targets(pod, o)?);
+    }
+    if targets.is_empty() {
+        return Err(anyhow::anyhow!(
+            "No containers in {} match {}",
+            pod_name,
+            o.container.to_str()
+        ));
+    }
+
+    stream_targets(ctx, o, targets).await
+}
+
+/// Follows every pod whose name matches `p`, attaching to pods as their containers start
+/// and retiring streams once their pod is deleted. Without `-f`, or with `--previous`, the
+/// logs of every matching pod are printed once instead.
+pub async fn follow_logs(ctx: &KubeContext, o: &LogsOpts, p: &str) -> Result<()> {
+    let re = Regex::new(p)?;
+    if !o.follow || o.previous {
+        return snapshot_logs(ctx, o, &re).await;
+    }
+
+    let (out, writer) = spawn_writer(o)?;
+    let opts = StreamOptions::new(o, out)?;
+    let mut colors = ColorAssigner::new(o)?;
+    let (tx, mut rx) = mpsc::channel(100);
+    watch_pods(
+        ctx,
+        pod_scope(o),
+        pod_list_params(&o.selector, &o.field_selector),
+        tx,
+    );
+
+    let mut followed: HashMap<String, Vec<FollowedStream>> = HashMap::new();
+    while let Some(event) = rx.recv().await {
+        match event {
+            PodEvent::Restarted(pods) => {
+                let names: HashSet<String> = pods.iter().map(|p| display_name(p, o)).collect();
+                let gone: Vec<String> = followed
+                    .keys()
+                    .filter(|n| !names.contains(*n))
+                    .cloned()
+                    .collect();
+                for name in gone {
+                    retire_pod(&opts, &mut colors, &mut followed, &name).await?;
+                }
+                for pod in &pods {
+                    attach_pod(ctx, o, &re, &opts, &mut colors, &mut followed, pod).await?;
+                }
+            }
+            PodEvent::Applied(pod) => {
+                attach_pod(ctx, o, &re, &opts, &mut colors, &mut followed, &pod).await?
+            }
+            PodEvent::Deleted(pod) => {
+                retire_pod(&opts, &mut colors, &mut followed, &display_name(&pod, o)).await?
+            }
+            PodEvent::Synced => {}
+            PodEvent::Error(e) => eprintln!("Pod watch error: {}", e),
+        }
+    }
+
+    drop(opts);
+    writer.await?
+}
+
+struct FollowedStream {
+    container: String,
+    color_key: String,
+    handle: AbortHandle,
+    /// Set when the stream ended with an error, so the container can be attached again.
+    failed: Arc<AtomicBool>,
+}
+
+async fn attach_pod(
+    ctx: &KubeContext,
+    o: &LogsOpts,
+    re: &Regex,
+    opts: &StreamOptions,
+    colors: &mut ColorAssigner,
+    followed: &mut HashMap<String, Vec<FollowedStream>>,
+    pod: &Pod,
+) -> Result<()> {
+    let name = display_name(pod, o);
+    if !re.is_match(&name) || pod.metadata.deletion_timestamp.is_some() {
+        return Ok(());
+    }
+
+    let running = running_containers(pod);
+    let streams = followed.entry(name).or_insert_with(Vec::new);
+    streams.retain(|s| {
+        let failed = s.failed.load(Ordering::Relaxed);
+        if failed {
+            colors.release(&s.color_key);
+        }
+        !failed
+    });
+    for target in log_targets(pod, o)? {
+        let container = target.container.clone().unwrap_or_default();
+        if !running.contains(&container) || streams.iter().any(|s| s.container == container) {
+            continue;
+        }
+
+        opts.marker('+', &target.prefix, Color::Green).await?;
+        let color_key = colors.key(&target.prefix, target.owner.as_deref());
+        let c = colors.assign(&color_key);
+        let prefix = target.prefix.clone();
+        let (stream, handle) = abortable(stream_logs(ctx.clone(), target, c, opts.clone()));
+        let failed = Arc::new(AtomicBool::new(false));
+        let flag = failed.clone();
+        task::spawn(async move {
+            if let Ok(Err(e)) = stream.await {
+                eprintln!("Failed to read logs for {}: {}", prefix, e);
+                flag.store(true, Ordering::Relaxed);
+            }
+        });
+        streams.push(FollowedStream {
+            container,
+            color_key,
+            handle,
+            failed,
+        });
+    }
+
+    Ok(())
+}
+
+async fn retire_pod(
+    opts: &StreamOptions,
+    colors: &mut ColorAssigner,
+    followed: &mut HashMap<String, Vec<FollowedStream>>,
+    name: &str,
+) -> Result<()> {
+    if let Some(streams) = followed.remove(name) {
+        if !streams.is_empty() {
+            opts.marker('-', name, Color::Red).await?;
+        }
+        for s in streams {
+            s.handle.abort();
+            colors.release(&s.color_key);
+        }
+    }
+
+    Ok(())
+}
+
+/// Containers that have logs to read: running now or terminated at least once.
+fn started_containers(pod: &Pod) -> HashSet<String> {
+    pod.status
+        .as_ref()
+        .and_then(|s| s.container_statuses.as_ref())
+        .map(|cs| {
+            cs.iter()
+                .filter(|c| {
+                    let running = c.state.as_ref().map_or(false, |s| s.waiting.is_none());
+                    let terminated = c
+                        .last_state
+                        .as_ref()
+                        .map_or(false, |s| s.terminated.is_some());
+                    running || terminated
+                })
+                .map(|c| c.name.clone())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn running_containers(pod: &Pod) -> HashSet<String> {
+    pod.status
+        .as_ref()
+        .and_then(|s| s.container_statuses.as_ref())
+        .map(|cs| {
+            cs.iter()
+                .filter(|c| c.state.as_ref().map_or(false, |s| s.running.is_some()))
+                .map(|c| c.name.clone())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+async fn snapshot_logs(ctx: &KubeContext, o: &LogsOpts, re: &Regex) -> Result<()> {
+    let lp = pod_list_params(&o.selector, &o.field_selector);
+    let pods: Vec<Pod> = get_pods(ctx, pod_scope(o), &lp)
+        .await?
+        .into_iter()
+        .filter(|p| re.is_match(&display_name(p, o)))
+        .collect();
+    if pods.is_empty() {
+        return Err(anyhow::anyhow!(
+            "No pods in {} match",
+            pod_scope(o).unwrap_or("any namespace")
+        ));
+    }
+
+    let mut targets = vec![];
+    for pod in &pods {
+        let started = if o.previous {
+            crashed_containers(pod).into_iter().collect()
+        } else {
+            started_containers(pod)
+        };
+        targets.extend(
+            log_targets(pod, o)?
+                .into_iter()
+                .filter(|t| started.contains(t.container.as_deref().unwrap_or(""))),
+        );
+    }
+    if targets.is_empty() {
+        let state = if o.previous { "crashed" } else { "started" };
+        return Err(anyhow::anyhow!("No {} containers matched", state));
+    }
+
+    stream_targets(ctx, o, targets).await
+}
+
+async fn stream_targets(ctx: &KubeContext, o: &LogsOpts, targets: Vec<LogTarget>) -> Result<()> {
+    let (out, writer) = spawn_writer(o)?;
+    let opts = StreamOptions::new(o, out)?;
+    let mut colors = ColorAssigner::new(o)?;
+    let mut tasks = vec![];
+    let mut prefixes = vec![];
+    for target in targets {
+        let color_key = colors.key(&target.prefix, target.owner.as_deref());
+        let c = colors.assign(&color_key);
+
+        prefixes.push(target.prefix.clone());
+        let t = task::spawn(stream_logs(ctx.clone(), target, c, opts.clone()));
         tasks.push(t);
     }
+    drop(opts);
+
+    let results = join_all(tasks).await;
+    let mut failed = 0;
+    for (prefix, result) in prefixes.iter().zip(results) {
+        let result = result.map_err(anyhow::Error::from).and_then(|r| r);
+        if let Err(e) = result {
+            eprintln!("Failed to read logs for {}: {}", prefix, e);
+            failed += 1;
+        }
+    }
+    writer.await??;
 
-    join_all(tasks).await;
+    if failed > 0 {
+        return Err(anyhow::anyhow!(
+            "{} of {} log streams failed",
+            failed,
+            prefixes.len()
+        ));
+    }
 
     Ok(())
 }
 
-pub async fn select_pod(o: &LogsOpts) -> Result<()> {
+pub async fn select_pod(ctx: &KubeContext, o: &LogsOpts) -> Result<()> {
     // println!("Ops: {:?}", o);
-    let pods = list_pods(&o.namespace).await?;
-    let mut input = String::new();
-    print!("Enter Pod number: ");
-    io::stdout().flush()?;
-    match io::stdin().read_line(&mut input) {
-        Ok(_) => {
-            let index = input.trim().parse::<usize>()?;
+    let lp = pod_list_params(&o.selector, &o.field_selector);
+    let pods = list_pods(ctx, o, &lp).await?;
+    match prompt("Enter Pod number: ") {
+        Ok(input) => {
+            let index = input.parse::<usize>()?;
             if let Some(p) = pods.get(&index) {
-                println!("{}", p);
-                let c = get_color()?;
-
-                let h = o.highlight.to_str();
-
-                stream_logs(
-                    o.namespace.clone(),
-                    p.clone(),
-                    o.tail_length,
-                    c,
-                    h,
-                    o.filter,
-                )
-                .await?;
+                println!("{}", display_name(p, o));
+
+                let targets = if o.container.is_none() && !o.all_containers {
+                    select_container(p, o)?
+                } else {
+                    log_targets(p, o)?
+                };
+
+                stream_targets(ctx, o, targets).await?;
             }
         }
         Err(error) => println!("error: {}", error),
@@ -131,118 +450,771 @@ pub async fn select_pod(o: &LogsOpts) -> Result<()> {
     Ok(())
 }
 
-async fn list_pods(namespace: &str) -> Result<HashMap<usize, String>> {
+fn select_container(pod: &Pod, o: &LogsOpts) -> Result<Vec<LogTarget>> {
+    let namespace = Meta::namespace(pod).unwrap_or_default();
+    let containers = pod_containers(pod);
+    if containers.len() <= 1 {
+        let container = containers.first().map(String::as_str);
+        let target = LogTarget::new(&namespace, pod, container, false);
+        return Ok(vec![qualify_target(target, o)]);
+    }
+
+    for (i, c) in containers.iter().enumerate() {
+        println!("\t{}: {}", i, c);
+    }
+    let input = prompt("Enter Container number (a for all): ")?;
+    if input == "a" {
+        return Ok(containers
+            .iter()
+            .map(|c| LogTarget::new(&namespace, pod, Some(c.as_str()), true))
+            .map(|t| qualify_target(t, o))
+            .collect());
+    }
+
+    let index = input.parse::<usize>()?;
+    match containers.get(index) {
+        Some(c) => {
+            let target = LogTarget::new(&namespace, pod, Some(c.as_str()), true);
+            Ok(vec![qualify_target(target, o)])
+        }
+        None => Err(anyhow::anyhow!("No container at index {}", index)),
+    }
+}
+
+pub fn prompt(message: &str) -> Result<String> {
+    let mut input = String::new();
+    print!("{}", message);
+    io::stdout().flush()?;
+    io::stdin().read_line(&mut input)?;
+
+    Ok(input.trim().to_string())
+}
+
+async fn list_pods(
+    ctx: &KubeContext,
+    o: &LogsOpts,
+    lp: &ListParams,
+) -> Result<HashMap<usize, Pod>> {
     // std::env::set_var("RUST_LOG", "info");
     // env_logger::init();
 
-    let pods = get_pods(namespace).await?;
+    let pods = get_pods(ctx, pod_scope(o), lp).await?;
     let mut pod_map = HashMap::new();
-    for (i, p) in pods.iter().enumerate() {
-        println!("\t{}: {}", i, Meta::name(p));
-        pod_map.insert(i, Meta::name(p));
+    for (i, p) in pods.into_iter().enumerate() {
+        println!("\t{}: {}", i, display_name(&p, o));
+        pod_map.insert(i, p);
     }
 
     Ok(pod_map)
 }
 
-async fn collect_pods(namespace: &str, pattern: &str) -> Result<Vec<String>> {
-    let mut client_config = match Config::infer().await {
-        Ok(c) => c,
-        Err(_) => Config::from_kubeconfig(&KubeConfigOptions::default()).await?,
-    };
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum LineKind {
+    Plain,
+    Debug,
+    Warn,
+    Error,
+    Highlight,
+    Notice,
+}
+
+pub fn classify_line(line: &str, highlight: Option<&Regex>) -> LineKind {
+    if line.contains("ERROR") || line.contains("error") || line.contains("Error") {
+        LineKind::Error
+    } else if highlight.map_or(false, |re| re.is_match(line)) {
+        LineKind::Highlight
+    } else {
+        LineKind::Plain
+    }
+}
+
+pub fn highlight_regex(highlight: &str) -> Result<Option<Regex>> {
+    if highlight.is_empty() {
+        Ok(None)
+    } else {
+        Ok(Some(Regex::new(highlight)?))
+    }
+}
+
+/// Turns raw log lines into display text, parsing JSON lines when a JSON format is configured
+/// and falling back to substring checks for everything else.
+#[derive(Clone, Debug)]
+pub struct LineFormatter {
+    highlight: Option<Regex>,
+    json: Option<JsonFormat>,
+    query: Option<Query>,
+}
+
+impl LineFormatter {
+    pub fn new(highlight: &str, json: &JsonOpts, query: Option<&str>) -> Result<Self> {
+        let query = match query {
+            Some(q) => Some(Query::parse(q, JsonFormat::from_keys(json))?),
+            None => None,
+        };
+
+        Ok(LineFormatter {
+            highlight: highlight_regex(highlight)?,
+            json: JsonFormat::new(json),
+            query,
+        })
+    }
+
+    pub fn matches(&self, text: &str) -> bool {
+        self.query.as_ref().map_or(true, |q| q.matches(text))
+    }
 
-    // client_config.timeout = std::time::Duration::from_secs(60 * 60 * 24);
-    client_config.timeout = None;
-    let client = Client::new(client_config);
+    pub fn is_highlighted(&self, text: &str) -> bool {
+        self.highlight
+            .as_ref()
+            .map_or(false, |re| re.is_match(text))
+    }
+
+    pub fn format(&self, text: &str) -> (LineKind, String) {
+        let obj = match self
+            .json
+            .as_ref()
+            .and_then(|j| j.parse(text).map(|o| (j, o)))
+        {
+            Some(o) => o,
+            None => {
+                return (
+                    classify_line(text, self.highlight.as_ref()),
+                    text.to_string(),
+                )
+            }
+        };
+
+        let (json, obj) = obj;
+        let rendered = json.render(&obj);
+        let kind = match json.level(&obj) {
+            Some(Level::Error) | Some(Level::Fatal) => LineKind::Error,
+            _ if self.is_highlighted(text) => LineKind::Highlight,
+            Some(Level::Warn) => LineKind::Warn,
+            Some(Level::Debug) | Some(Level::Trace) => LineKind::Debug,
+            _ => LineKind::Plain,
+        };
+
+        (kind, rendered)
+    }
+}
+
+pub fn follow_params(tail_lines: i64, container: Option<String>) -> LogParams {
+    let mut lp = LogParams::default();
+    lp.container = container;
+    lp.follow = true;
+    lp.pretty = true;
+    lp.tail_lines = Some(tail_lines);
+    lp
+}
 
-    let pods: Api<Pod> = Api::namespaced(client, namespace);
-    let mut lp = ListParams::default();
-    lp.timeout = None;
-    let mut matching_pods = vec![];
-    let re: Regex = Regex::new(pattern).unwrap();
-    for (_, p) in (pods.list(&lp).await?).into_iter().enumerate() {
-        // lazy_static! {
-        //     static ref RE: Regex = Regex::new(pattern).unwrap();
-        // }
-        let name = Meta::name(&p).to_string();
-        if re.is_match(&name) {
-            matching_pods.push(name);
+/// The span of log lines to fetch: the last `tail_lines`, or everything between `since`
+/// and `until` once either bound is given.
+#[derive(Copy, Clone, Debug)]
+pub struct LogWindow {
+    follow: bool,
+    previous: bool,
+    tail_lines: i64,
+    since: Option<DateTime<Utc>>,
+    until: Option<DateTime<Utc>>,
+}
+
+impl LogWindow {
+    pub fn new(o: &LogsOpts) -> Result<Self> {
+        let since = match (&o.since, &o.since_time) {
+            (Some(d), _) => Some(parse_ago(d)?),
+            (None, Some(t)) => Some(parse_time(t)?),
+            (None, None) => None,
+        };
+        let until = o.until.as_deref().map(parse_time).transpose()?;
+        if let (Some(s), Some(u)) = (since, until) {
+            if u <= s {
+                return Err(anyhow::anyhow!("--until must be later than --since"));
+            }
+        }
+
+        Ok(LogWindow {
+            follow: o.follow && !o.previous,
+            previous: o.previous,
+            tail_lines: o.tail_length,
+            since,
+            until,
+        })
+    }
+
+    /// The API only takes whole seconds, so the stream may start slightly early; lines before
+    /// `since` are dropped again by `contains`.
+    pub fn params(&self, container: Option<String>) -> LogParams {
+        let mut lp = follow_params(self.tail_lines, container);
+        if let Some(since) = self.since {
+            let secs = Utc::now().signed_duration_since(since).num_seconds() + 1;
+            lp.tail_lines = None;
+            lp.since_seconds = Some(secs.max(1));
+        } else if self.until.is_some() {
+            lp.tail_lines = None;
+        }
+        if !self.follow || (self.until.is_some() && self.deadline().is_none()) {
+            lp.follow = false;
+        }
+        lp.previous = self.previous;
+        lp
+    }
+
+    pub fn contains(&self, timestamp: Option<DateTime<Utc>>) -> bool {
+        let started = match (timestamp, self.since) {
+            (Some(t), Some(s)) => t >= s,
+            _ => true,
+        };
+
+        started && !self.is_past(timestamp)
+    }
+
+    pub fn is_past(&self, timestamp: Option<DateTime<Utc>>) -> bool {
+        match (timestamp, self.until) {
+            (Some(t), Some(u)) => t > u,
+            _ => false,
         }
     }
 
-    Ok(matching_pods)
+    /// When a live stream should be cut off; `None` without `--until` or once it has passed,
+    /// in which case the stream is not followed at all.
+    pub fn deadline(&self) -> Option<Instant> {
+        if !self.follow {
+            return None;
+        }
+        let remaining = self
+            .until?
+            .signed_duration_since(Utc::now())
+            .to_std()
+            .ok()?;
+        Some(Instant::now() + remaining)
+    }
 }
 
-pub async fn stream_logs(
+#[derive(Clone, Debug)]
+pub enum LogEvent {
+    Line {
+        timestamp: Option<DateTime<Utc>>,
+        text: String,
+    },
+    /// Out-of-band messages about the stream itself, e.g. reconnects and restarts.
+    Notice(String),
+}
+
+/// Where a dropped stream should pick up again: the timestamp of the last line we sent and
+/// how many lines carried that exact timestamp, so a replay can skip them.
+#[derive(Default)]
+struct ResumePoint {
+    last: Option<DateTime<Utc>>,
+    seen: usize,
+    skip: usize,
+}
+
+impl ResumePoint {
+    fn accept(&mut self, timestamp: Option<DateTime<Utc>>) -> bool {
+        let (t, last) = match (timestamp, self.last) {
+            (Some(t), Some(last)) => (t, last),
+            (Some(t), None) => {
+                self.last = Some(t);
+                self.seen = 1;
+                return true;
+            }
+            (None, _) => return true,
+        };
+
+        if t < last {
+            false
+        } else if t == last {
+            if self.skip > 0 {
+                self.skip -= 1;
+                return false;
+            }
+            self.seen += 1;
+            true
+        } else {
+            self.last = Some(t);
+            self.seen = 1;
+            self.skip = 0;
+            true
+        }
+    }
+
+    fn resume(&mut self, lp: &mut LogParams) {
+        if let Some(last) = self.last {
+            let since = Utc::now().signed_duration_since(last).num_seconds() + 1;
+            lp.tail_lines = None;
+            lp.since_seconds = Some(since.max(1));
+            self.skip = self.seen;
+        }
+    }
+}
+
+/// Reads the log stream of a pod and sends it to `tx` one complete line at a time. When
+/// following, dropped streams are reopened with backoff and resumed after the last line sent.
+pub async fn read_log_lines(
+    ctx: KubeContext,
     namespace: String,
     pod_name: String,
-    tail_lines: i64,
-    c: Color,
-    highlight: String,
-    filter: bool,
+    lp: LogParams,
+    mut tx: mpsc::Sender<LogEvent>,
 ) -> Result<()> {
-    let mut client_config = match Config::infer().await {
-        Ok(c) => c,
-        Err(_) => Config::from_kubeconfig(&KubeConfigOptions::default()).await?,
+    let pods = ctx.pods(&namespace);
+    let mut lp = lp;
+    lp.timestamps = true;
+    let container = lp.container.clone();
+    let mut resume = ResumePoint::default();
+    let mut restarts = match ctx.request(pods.get(&pod_name)).await {
+        Ok(p) => restart_count(&p, container.as_deref()),
+        Err(_) => None,
     };
+    let mut backoff = RECONNECT_BACKOFF_MIN;
 
-    // client_config.timeout = std::time::Duration::from_secs(60 * 60 * 24);
-    client_config.timeout = None;
-    let client = Client::new(client_config);
+    loop {
+        let result = read_log_stream(&ctx, &pods, &pod_name, &lp, &mut resume, &mut tx).await;
+        match result {
+            Ok(true) => return Ok(()),
+            Ok(false) => backoff = RECONNECT_BACKOFF_MIN,
+            Err(e) if !lp.follow || (resume.last.is_none() && restarts.is_none()) => return Err(e),
+            Err(e) => {
+                let notice = LogEvent::Notice(format!("log stream interrupted: {}", e));
+                if tx.send(notice).await.is_err() {
+                    return Ok(());
+                }
+            }
+        }
 
-    let pods: Api<Pod> = Api::namespaced(client, &namespace);
-    let mut lp = LogParams::default();
-    lp.follow = true;
-    lp.pretty = true;
-    lp.tail_lines = Some(tail_lines);
-    let mut logs = pods.log_stream(&pod_name, &lp).await?.boxed();
-    let re: Regex = Regex::new(&highlight).unwrap();
-    execute!(stdout(), ResetColor)?;
-    while let Some(line) = logs.try_next().await? {
-        let line_str = String::from_utf8((&line).to_vec())?;
-        if filter {
-            if !highlight.is_empty() && re.is_match(&line_str) {
-                execute!(
-                    stdout(),
-                    SetForegroundColor(Color::Yellow),
-                    SetAttribute(Attribute::Bold),
-                    Print(line_str),
-                    ResetColor
-                )?;
-                println!();
+        if !lp.follow {
+            return Ok(());
+        }
+
+        delay_for(backoff).await;
+        backoff = (backoff * 2).min(RECONNECT_BACKOFF_MAX);
+
+        let pod = match ctx.request(pods.get(&pod_name)).await {
+            Ok(p) => p,
+            Err(e) if is_not_found(&e) => {
+                let _ = tx.send(LogEvent::Notice(String::from("pod deleted"))).await;
+                return Ok(());
+            }
+            Err(_) => continue,
+        };
+        if pod.metadata.deletion_timestamp.is_some() || pod_finished(&pod) {
+            return Ok(());
+        }
+
+        let count = restart_count(&pod, container.as_deref());
+        if let (Some(before), Some(now)) = (restarts, count) {
+            if now > before {
+                let notice = LogEvent::Notice(format!(
+                    "container {} restarted (restart count {})",
+                    container.as_deref().unwrap_or(&pod_name),
+                    now
+                ));
+                if tx.send(notice).await.is_err() {
+                    return Ok(());
+                }
+            }
+        }
+        restarts = count;
+        resume.resume(&mut lp);
+    }
+}
+
+/// Reads a single log stream to its end. Returns `true` once the receiver has gone away.
+async fn read_log_stream(
+    ctx: &KubeContext,
+    pods: &Api<Pod>,
+    pod_name: &str,
+    lp: &LogParams,
+    resume: &mut ResumePoint,
+    tx: &mut mpsc::Sender<LogEvent>,
+) -> Result<bool> {
+    let mut logs = ctx.request(pods.log_stream(pod_name, lp)).await?.boxed();
+    let mut buf = LineBuffer::default();
+    while let Some(chunk) = logs.try_next().await? {
+        for line in buf.push(&chunk) {
+            if !send_line(&line, resume, tx).await {
+                return Ok(true);
+            }
+        }
+    }
+
+    if let Some(line) = buf.finish() {
+        if !send_line(&line, resume, tx).await {
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
+/// Splits a log stream into lines. Bytes are only decoded once their whole line has arrived,
+/// since a chunk can end part way through a multi-byte character.
+#[derive(Default)]
+struct LineBuffer {
+    buf: Vec<u8>,
+}
+
+impl LineBuffer {
+    /// Adds `chunk` and returns the lines it completed, without their line endings.
+    fn push(&mut self, chunk: &[u8]) -> Vec<String> {
+        self.buf.extend_from_slice(chunk);
+        let end = match self.buf.iter().rposition(|b| *b == b'\n') {
+            Some(i) => i,
+            None => return vec![],
+        };
+        let rest = self.buf.split_off(end + 1);
+        let complete = std::mem::replace(&mut self.buf, rest);
+
+        complete[..end]
+            .split(|b| *b == b'\n')
+            .map(decode_line)
+            .collect()
+    }
+
+    /// The last line, when the stream did not end with a newline.
+    fn finish(self) -> Option<String> {
+        if self.buf.is_empty() {
+            None
+        } else {
+            Some(decode_line(&self.buf))
+        }
+    }
+}
+
+fn decode_line(line: &[u8]) -> String {
+    String::from_utf8_lossy(line)
+        .trim_end_matches('\r')
+        .to_string()
+}
+
+async fn send_line(line: &str, resume: &mut ResumePoint, tx: &mut mpsc::Sender<LogEvent>) -> bool {
+    let (timestamp, text) = split_timestamp(line);
+    if !resume.accept(timestamp) {
+        return true;
+    }
+
+    tx.send(LogEvent::Line {
+        timestamp,
+        text: text.to_string(),
+    })
+    .await
+    .is_ok()
+}
+
+fn split_timestamp(line: &str) -> (Option<DateTime<Utc>>, &str) {
+    if let Some(i) = line.find(' ') {
+        if let Ok(t) = DateTime::parse_from_rfc3339(&line[..i]) {
+            return (Some(t.with_timezone(&Utc)), &line[i + 1..]);
+        }
+    }
+
+    (None, line)
+}
+
+fn restart_count(pod: &Pod, container: Option<&str>) -> Option<i32> {
+    pod.status
+        .as_ref()?
+        .container_statuses
+        .as_ref()?
+        .iter()
+        .find(|c| container.map_or(true, |n| c.name == n))
+        .map(|c| c.restart_count)
+}
+
+fn pod_finished(pod: &Pod) -> bool {
+    let phase = pod.status.as_ref().and_then(|s| s.phase.as_deref());
+    matches!(phase, Some("Succeeded") | Some("Failed"))
+}
+
+fn is_not_found(e: &anyhow::Error) -> bool {
+    match e.downcast_ref::<kube::Error>() {
+        Some(kube::Error::Api(ae)) => ae.code == 404,
+        _ => false,
+    }
+}
+
+/// Settings shared by every stream of a `logs` invocation, along with the channel to the
+/// single writer that prints their lines.
+#[derive(Clone)]
+pub struct StreamOptions {
+    window: LogWindow,
+    formatter: LineFormatter,
+    filter: bool,
+    out: mpsc::Sender<Output>,
+}
+
+impl StreamOptions {
+    pub fn new(o: &LogsOpts, out: mpsc::Sender<Output>) -> Result<Self> {
+        Ok(StreamOptions {
+            window: LogWindow::new(o)?,
+            formatter: LineFormatter::new(&o.highlight.to_str(), &o.json, o.query.as_deref())?,
+            filter: o.filter,
+            out,
+        })
+    }
+
+    async fn marker(&self, marker: char, name: &str, color: Color) -> Result<()> {
+        let output = Output::Marker {
+            marker,
+            name: name.to_string(),
+            color,
+        };
+        self.out
+            .clone()
+            .send(output)
+            .await
+            .map_err(|_| anyhow::anyhow!("log writer has stopped"))
+    }
+}
+
+pub enum Output {
+    Line {
+        target: Arc<LogTarget>,
+        color: Color,
+        timestamp: Option<DateTime<Utc>>,
+        raw: String,
+        kind: LineKind,
+        text: String,
+    },
+    Notice {
+        target: Arc<LogTarget>,
+        color: Color,
+        text: String,
+    },
+    Marker {
+        marker: char,
+        name: String,
+        color: Color,
+    },
+}
+
+impl Output {
+    fn timestamp(&self) -> Option<DateTime<Utc>> {
+        match self {
+            Output::Line { timestamp, .. } => *timestamp,
+            _ => None,
+        }
+    }
+
+    fn save(&self, saver: &mut LogSaver) -> Result<()> {
+        match self {
+            Output::Line {
+                target,
+                timestamp: Some(t),
+                raw,
+                ..
+            } => saver.write(target, &format!("{} {}", t.to_rfc3339(), raw)),
+            Output::Line { target, raw, .. } => saver.write(target, raw),
+            Output::Notice { target, text, .. } => {
+                saver.write(target, &format!("--- {} ---", text))
             }
+            Output::Marker { .. } => Ok(()),
+        }
+    }
+
+    fn print(&self, printer: &Printer) -> Result<()> {
+        match self {
+            Output::Line {
+                target,
+                color,
+                timestamp,
+                raw,
+                kind,
+                text,
+            } => printer.line(target, *color, *timestamp, raw, *kind, text),
+            Output::Notice {
+                target,
+                color,
+                text,
+            } => printer.notice(target, *color, text),
+            Output::Marker {
+                marker,
+                name,
+                color,
+            } => printer.marker(*marker, name, *color),
+        }
+    }
+}
+
+fn spawn_writer(o: &LogsOpts) -> Result<(mpsc::Sender<Output>, task::JoinHandle<Result<()>>)> {
+    let (tx, rx) = mpsc::channel(1000);
+    let printer = Printer::new(o.output, !o.filter, o.timestamps);
+    let saver = LogSaver::new(o)?;
+    let merge = Duration::from_millis(o.merge_window);
+    Ok((tx, task::spawn(write_output(printer, saver, merge, rx))))
+}
+
+/// The single writer behind every stream. Lines are held for up to `merge` after they arrive
+/// and released in timestamp order, so output from several pods reads chronologically; lines
+/// without a timestamp are ordered by when they arrived. With `--save-dir` each line is also
+/// written to its stream's file, until a write fails.
+async fn write_output(
+    printer: Printer,
+    mut saver: Option<LogSaver>,
+    merge: Duration,
+    mut rx: mpsc::Receiver<Output>,
+) -> Result<()> {
+    let mut pending: BTreeMap<(DateTime<Utc>, u64), (Instant, Output)> = BTreeMap::new();
+    let mut seq = 0;
+    let tick = (merge / 4).max(Duration::from_millis(10));
+    loop {
+        let received = if pending.is_empty() {
+            rx.recv().await.map(Some)
         } else {
-            execute!(
-                stdout(),
-                SetForegroundColor(c),
-                Print(&pod_name),
-                Print(" ")
-            )?;
-            if line_str.contains("ERROR")
-                || line_str.contains("error")
-                || line_str.contains("Error")
-            {
-                execute!(
-                    stdout(),
-                    SetForegroundColor(Color::Red),
-                    SetAttribute(Attribute::Bold),
-                    Print(line_str),
-                    ResetColor
-                )?;
-            } else if !highlight.is_empty() && re.is_match(&line_str) {
-                execute!(
-                    stdout(),
-                    SetForegroundColor(Color::Yellow),
-                    SetAttribute(Attribute::Bold),
-                    Print(line_str),
-                    ResetColor
-                )?;
-            } else {
-                execute!(stdout(), ResetColor, Print(line_str))?;
-            }
-            println!();
+            tokio::select! {
+                output = rx.recv() => output.map(Some),
+                _ = delay_for(tick) => Some(None),
+            }
+        };
+
+        match received {
+            Some(Some(output)) => {
+                let key = (output.timestamp().unwrap_or_else(Utc::now), seq);
+                seq += 1;
+                pending.insert(key, (Instant::now(), output));
+            }
+            Some(None) => {}
+            None => break,
+        }
+
+        // Release everything up to the newest line that has waited out the window; anything
+        // older than it can no longer be overtaken.
+        let now = Instant::now();
+        let cutoff = pending
+            .iter()
+            .filter(|(_, (arrived, _))| now.duration_since(*arrived) >= merge)
+            .map(|(k, _)| *k)
+            .max();
+        if let Some((t, n)) = cutoff {
+            let rest = pending.split_off(&(t, n + 1));
+            for (_, (_, output)) in std::mem::replace(&mut pending, rest) {
+                emit(&output, &printer, &mut saver)?;
+            }
         }
     }
 
+    for (_, (_, output)) in pending {
+        emit(&output, &printer, &mut saver)?;
+    }
+
     Ok(())
 }
+
+/// Saves and prints `output`. A failed save is reported once and turns saving off, rather than
+/// ending the stream.
+fn emit(output: &Output, printer: &Printer, saver: &mut Option<LogSaver>) -> Result<()> {
+    if let Some(Err(e)) = saver.as_mut().map(|s| output.save(s)) {
+        eprintln!("Error saving logs, no longer saving: {:#}", e);
+        *saver = None;
+    }
+
+    output.print(printer)
+}
+
+pub async fn stream_logs(
+    ctx: KubeContext,
+    target: LogTarget,
+    c: Color,
+    opts: StreamOptions,
+) -> Result<()> {
+    let StreamOptions {
+        window,
+        formatter,
+        filter,
+        mut out,
+    } = opts;
+    let target = Arc::new(target);
+    let lp = window.params(target.container.clone());
+    let (tx, mut rx) = mpsc::channel(100);
+    let (reader, handle) = abortable(read_log_lines(
+        ctx,
+        target.namespace.clone(),
+        target.pod.clone(),
+        lp,
+        tx,
+    ));
+    let reader = task::spawn(reader);
+
+    let deadline = window.deadline();
+    loop {
+        let event = match deadline {
+            Some(d) => match timeout_at(d, rx.recv()).await {
+                Ok(e) => e,
+                Err(_) => break,
+            },
+            None => rx.recv().await,
+        };
+
+        let output = match event {
+            Some(LogEvent::Line { timestamp, text }) => {
+                if window.is_past(timestamp) {
+                    break;
+                }
+                if !window.contains(timestamp) || !formatter.matches(&text) {
+                    continue;
+                }
+                let (kind, line_str) = formatter.format(&text);
+                if filter && !formatter.is_highlighted(&line_str) {
+                    continue;
+                }
+                Output::Line {
+                    target: target.clone(),
+                    color: c,
+                    timestamp,
+                    raw: text,
+                    kind: if filter { LineKind::Highlight } else { kind },
+                    text: line_str,
+                }
+            }
+            Some(LogEvent::Notice(n)) => Output::Notice {
+                target: target.clone(),
+                color: c,
+                text: n,
+            },
+            None => break,
+        };
+        if out.send(output).await.is_err() {
+            break;
+        }
+    }
+
+    handle.abort();
+    match reader.await? {
+        Ok(result) => result,
+        Err(_) => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn line_buffer_splits_lines_across_chunks() {
+        let mut buf = LineBuffer::default();
+        assert!(buf.push(b"first ").is_empty());
+        assert_eq!(
+            buf.push(b"line\r\nsecond\nthi"),
+            vec!["first line", "second"]
+        );
+        assert_eq!(buf.push(b"rd\n\n"), vec!["third", ""]);
+        assert!(buf.push(b"last").is_empty());
+        assert_eq!(buf.finish(), Some(String::from("last")));
+    }
+
+    #[test]
+    fn line_buffer_keeps_characters_split_across_chunks() {
+        let line = "{\"msg\":\"caf\u{e9} \u{1f600}\"}\n".as_bytes();
+        let e = line.iter().position(|b| *b == 0xc3).unwrap() + 1;
+        let emoji = line.iter().position(|b| *b == 0xf0).unwrap() + 2;
+
+        let mut buf = LineBuffer::default();
+        assert!(buf.push(&line[..e]).is_empty());
+        assert!(buf.push(&line[e..emoji]).is_empty());
+        assert_eq!(
+            buf.push(&line[emoji..]),
+            vec!["{\"msg\":\"caf\u{e9} \u{1f600}\"}"]
+        );
+        assert_eq!(buf.finish(), None);
+    }
+}
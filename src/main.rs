@@ -1,10 +1,25 @@
-use crate::util::OptionEx;
+use crate::{
+    config::{Config, KeyMap},
+    context::KubeContext,
+    output::OutputFormat,
+};
 use anyhow::Result;
-use clap::Clap;
+use clap::{ArgMatches, Clap, FromArgMatches, IntoApp};
 
+mod color;
+mod config;
+mod context;
+mod describe;
 mod logs;
+mod output;
+mod query;
+mod resources;
+mod save;
+mod scale;
+mod structured;
 mod ui;
 mod util;
+mod watch;
 
 #[derive(Clap)]
 #[clap(version = "0.1.0", author = "Jonathan Rothberg")]
@@ -19,39 +34,160 @@ enum SubCmd {
     Logs(LogsOpts),
     #[clap(name = "ui")]
     UI(UIOpts),
+    #[clap(name = "scale")]
+    Scale(ScaleOpts),
+}
+
+#[derive(Debug, Clap)]
+pub struct ClusterOpts {
+    #[clap(long = "context")]
+    context: Option<String>,
+    #[clap(long = "kubeconfig")]
+    kubeconfig: Option<String>,
+    #[clap(long = "request-timeout", default_value = "60")]
+    request_timeout: u64,
+    #[clap(long = "profile")]
+    profile: Option<String>,
+}
+
+#[derive(Debug, Clap)]
+pub struct JsonOpts {
+    #[clap(long = "json")]
+    json: bool,
+    #[clap(long = "fields")]
+    fields: Option<String>,
+    #[clap(long = "level-key", default_value = "level,lvl,severity")]
+    level_key: String,
+    #[clap(long = "message-key", default_value = "msg,message")]
+    message_key: String,
+    #[clap(long = "time-key", default_value = "ts,time,timestamp,@timestamp")]
+    time_key: String,
 }
 
 #[derive(Debug, Clap)]
 pub struct LogsOpts {
+    #[clap(flatten)]
+    cluster: ClusterOpts,
     #[clap(long = "pod")]
     pod: Option<String>,
     #[clap(short = 'f')]
     follow: bool,
-    #[clap(short = 'n', default_value = "nuwolf")]
+    #[clap(short = 'n', default_value = "default")]
     namespace: String,
+    #[clap(short = 'A', long = "all-namespaces")]
+    all_namespaces: bool,
     #[clap(short = 't', long = "tail-length", default_value = "100")]
     tail_length: i64,
     #[clap(short = 'p', long = "pattern")]
     pattern: Option<String>,
     #[clap(short = 'r', long = "terms")]
     terms: Option<String>,
-    #[clap(short = 'l', long = "highlight")]
+    #[clap(long = "highlight")]
     highlight: Option<String>,
     #[clap(short = 'r', long = "filter")]
     filter: bool,
+    #[clap(short = 'c', long = "container")]
+    container: Option<String>,
+    #[clap(long = "all-containers")]
+    all_containers: bool,
+    #[clap(short = 'l', long = "selector")]
+    selector: Option<String>,
+    #[clap(long = "field-selector")]
+    field_selector: Option<String>,
+    #[clap(flatten)]
+    json: JsonOpts,
+    #[clap(short = 'q', long = "query")]
+    query: Option<String>,
+    #[clap(
+        short = 'o',
+        long = "output",
+        default_value = "pretty",
+        possible_values = &["pretty", "raw", "jsonl", "logfmt"]
+    )]
+    output: OutputFormat,
+    #[clap(long = "since")]
+    since: Option<String>,
+    #[clap(long = "since-time", conflicts_with = "since")]
+    since_time: Option<String>,
+    #[clap(long = "until")]
+    until: Option<String>,
+    #[clap(long = "timestamps")]
+    timestamps: bool,
+    #[clap(long = "merge-window", default_value = "250")]
+    merge_window: u64,
+    #[clap(long = "previous")]
+    previous: bool,
+    #[clap(long = "save-dir")]
+    save_dir: Option<String>,
+    #[clap(long = "max-file-size", default_value = "100M")]
+    max_file_size: String,
+    #[clap(long = "max-files", default_value = "5")]
+    max_files: usize,
+    #[clap(long = "compress")]
+    compress: bool,
+    #[clap(long = "color-by", default_value = "pod", possible_values = &["pod", "owner"])]
+    color_by: String,
+    #[clap(long = "palette")]
+    palette: Option<String>,
 }
 
 #[derive(Debug, Clap)]
 pub struct UIOpts {
-    #[clap(short = 'n', default_value = "nuwolf")]
+    #[clap(flatten)]
+    cluster: ClusterOpts,
+    #[clap(short = 'n', default_value = "default")]
     namespace: String,
+    #[clap(short = 'A', long = "all-namespaces")]
+    all_namespaces: bool,
+    #[clap(short = 't', long = "tail-length", default_value = "100")]
+    tail_length: i64,
+    #[clap(long = "highlight")]
+    highlight: Option<String>,
+    #[clap(short = 'l', long = "selector")]
+    selector: Option<String>,
+    #[clap(long = "field-selector")]
+    field_selector: Option<String>,
+    #[clap(flatten)]
+    json: JsonOpts,
+    #[clap(skip)]
+    keys: KeyMap,
+}
+
+#[derive(Debug, Clap)]
+pub struct ScaleOpts {
+    #[clap(flatten)]
+    cluster: ClusterOpts,
+    name: String,
+    #[clap(short = 'n', default_value = "default")]
+    namespace: String,
+    #[clap(
+        short = 'k',
+        long = "kind",
+        default_value = "deployment",
+        possible_values = &["deployment", "statefulset"]
+    )]
+    kind: String,
+    #[clap(short = 'r', long = "replicas")]
+    replicas: i32,
+    #[clap(short = 'y', long = "yes")]
+    yes: bool,
+    #[clap(long = "no-wait")]
+    no_wait: bool,
+    #[clap(long = "timeout", default_value = "300")]
+    timeout: u64,
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
     env_logger::init();
     std::env::set_var("RUST_LOG", "trace");
-    let opts: Opts = Opts::parse();
+    let matches = Opts::into_app().get_matches();
+    let mut opts = Opts::from_arg_matches(&matches);
+
+    if let Err(e) = apply_config(&mut opts, &matches) {
+        eprintln!("Error in config: {:?}", e);
+        return Err(e);
+    }
 
     match run(&opts).await {
         Ok(_) => Ok(()),
@@ -62,35 +198,61 @@ async fn main() -> Result<()> {
     }
 }
 
-async fn run(opts: &Opts) -> Result<()> {
-    match &opts.subcmd {
-        SubCmd::Logs(o) => match &o.pod {
-            Some(p) => {
-                let c = logs::get_color()?;
+/// Fills in whatever was not given on the command line from the config file.
+fn apply_config(opts: &mut Opts, matches: &ArgMatches) -> Result<()> {
+    let config = Config::load()?;
+    match &mut opts.subcmd {
+        SubCmd::Logs(o) => {
+            let settings = config.settings(o.cluster.profile.as_deref())?;
+            if let Some(m) = matches.subcommand_matches("logs") {
+                settings.apply_logs(o, m)?;
+            }
+        }
+        SubCmd::UI(o) => {
+            let settings = config.settings(o.cluster.profile.as_deref())?;
+            if let Some(m) = matches.subcommand_matches("ui") {
+                settings.apply_ui(o, m)?;
+            }
+        }
+        SubCmd::Scale(o) => {
+            let settings = config.settings(o.cluster.profile.as_deref())?;
+            if let Some(m) = matches.subcommand_matches("scale") {
+                settings.apply_scale(o, m);
+            }
+        }
+    }
 
-                let h = o.highlight.to_str();
+    Ok(())
+}
 
-                logs::stream_logs(
-                    o.namespace.clone(),
-                    p.to_string(),
-                    o.tail_length,
-                    c,
-                    h,
-                    o.filter,
-                )
-                .await?;
-            }
-            None => match o.pattern {
-                Some(ref p) => {
-                    logs::follow_logs(&o, &p).await?;
-                }
-                None => {
-                    logs::select_pod(&o).await?;
+async fn run(opts: &Opts) -> Result<()> {
+    match &opts.subcmd {
+        SubCmd::Logs(o) => {
+            let ctx = KubeContext::new(&o.cluster).await?;
+            match &o.pod {
+                Some(p) => {
+                    logs::pod_logs(&ctx, &o, &p).await?;
                 }
-            },
-        },
+                None => match o.pattern {
+                    Some(ref p) => {
+                        logs::follow_logs(&ctx, &o, &p).await?;
+                    }
+                    None if o.selector.is_some() || o.field_selector.is_some() => {
+                        logs::follow_logs(&ctx, &o, "").await?;
+                    }
+                    None => {
+                        logs::select_pod(&ctx, &o).await?;
+                    }
+                },
+            }
+        }
         SubCmd::UI(o) => {
-            ui::load_ui(&o.namespace, &o).await?;
+            let ctx = KubeContext::new(&o.cluster).await?;
+            ui::load_ui(&ctx, &o.namespace, &o).await?;
+        }
+        SubCmd::Scale(o) => {
+            let ctx = KubeContext::new(&o.cluster).await?;
+            scale::scale(&ctx, &o).await?;
         }
     }
 
@@ -0,0 +1,207 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, SecondsFormat, Utc};
+use crossterm::{
+    queue,
+    style::{Attribute, Color, Print, ResetColor, SetAttribute, SetForegroundColor},
+    tty::IsTty,
+};
+use serde_json::json;
+use std::{
+    io::{stdout, Write},
+    str::FromStr,
+};
+
+use crate::logs::{LineKind, LogTarget};
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum OutputFormat {
+    Pretty,
+    Raw,
+    Jsonl,
+    Logfmt,
+}
+
+impl FromStr for OutputFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "pretty" => Ok(OutputFormat::Pretty),
+            "raw" => Ok(OutputFormat::Raw),
+            "jsonl" => Ok(OutputFormat::Jsonl),
+            "logfmt" => Ok(OutputFormat::Logfmt),
+            _ => Err(anyhow!(
+                "unknown output format '{}' (expected pretty, raw, jsonl or logfmt)",
+                s
+            )),
+        }
+    }
+}
+
+/// Writes log lines to stdout in the selected format. Only `pretty` output is coloured,
+/// and only when stdout is a terminal and `NO_COLOR` is unset; pod markers and stream
+/// notices go to stderr for the machine readable formats so stdout stays parseable.
+#[derive(Copy, Clone, Debug)]
+pub struct Printer {
+    format: OutputFormat,
+    color: bool,
+    prefix: bool,
+    timestamps: bool,
+}
+
+impl Printer {
+    pub fn new(format: OutputFormat, prefix: bool, timestamps: bool) -> Self {
+        let color = format == OutputFormat::Pretty
+            && stdout().is_tty()
+            && std::env::var_os("NO_COLOR").is_none();
+
+        Printer {
+            format,
+            color,
+            prefix,
+            timestamps,
+        }
+    }
+
+    /// Prints one log line; `raw` is the line as read from the container and `text` the
+    /// rendering used by the pretty output.
+    pub fn line(
+        &self,
+        target: &LogTarget,
+        c: Color,
+        timestamp: Option<DateTime<Utc>>,
+        raw: &str,
+        kind: LineKind,
+        text: &str,
+    ) -> Result<()> {
+        let out = stdout();
+        let mut out = out.lock();
+        if self.timestamps {
+            if let (OutputFormat::Pretty, Some(t)) | (OutputFormat::Raw, Some(t)) =
+                (self.format, timestamp)
+            {
+                self.styled(&mut out, &format_timestamp(t), Some(Color::DarkGrey), false)?;
+                write!(out, " ")?;
+            }
+        }
+        match self.format {
+            OutputFormat::Pretty => {
+                if self.prefix {
+                    self.styled(&mut out, &target.prefix, Some(c), false)?;
+                    write!(out, " ")?;
+                }
+                let (color, bold) = match kind {
+                    LineKind::Error => (Some(Color::Red), true),
+                    LineKind::Highlight => (Some(Color::Yellow), true),
+                    LineKind::Warn => (Some(Color::Yellow), false),
+                    LineKind::Debug => (Some(Color::DarkGrey), false),
+                    LineKind::Notice => (Some(Color::Cyan), false),
+                    LineKind::Plain => (None, false),
+                };
+                self.styled(&mut out, text, color, bold)?;
+                writeln!(out)?;
+            }
+            OutputFormat::Raw => writeln!(out, "{}", raw)?,
+            OutputFormat::Jsonl => {
+                let record = json!({
+                    "namespace": target.namespace,
+                    "pod": target.pod,
+                    "container": target.container,
+                    "timestamp": timestamp.map(format_timestamp),
+                    "message": raw,
+                });
+                writeln!(out, "{}", record)?;
+            }
+            OutputFormat::Logfmt => {
+                let ts = timestamp.map(format_timestamp).unwrap_or_default();
+                writeln!(
+                    out,
+                    "ts={} namespace={} pod={} container={} msg={}",
+                    logfmt_value(&ts),
+                    logfmt_value(&target.namespace),
+                    logfmt_value(&target.pod),
+                    logfmt_value(target.container.as_deref().unwrap_or("")),
+                    logfmt_value(raw)
+                )?;
+            }
+        }
+        out.flush()?;
+
+        Ok(())
+    }
+
+    pub fn notice(&self, target: &LogTarget, c: Color, notice: &str) -> Result<()> {
+        let text = format!("--- {} ---", notice);
+        if self.format != OutputFormat::Pretty {
+            eprintln!("{} {}", target.prefix, text);
+            return Ok(());
+        }
+
+        let out = stdout();
+        let mut out = out.lock();
+        self.styled(&mut out, &target.prefix, Some(c), false)?;
+        write!(out, " ")?;
+        if self.color {
+            queue!(out, SetAttribute(Attribute::Italic))?;
+        }
+        self.styled(&mut out, &text, Some(Color::Cyan), false)?;
+        writeln!(out)?;
+        out.flush()?;
+
+        Ok(())
+    }
+
+    /// Prints a `+ pod` / `- pod` marker as pods are attached and retired.
+    pub fn marker(&self, marker: char, name: &str, c: Color) -> Result<()> {
+        let text = format!("{} {}", marker, name);
+        if self.format != OutputFormat::Pretty {
+            eprintln!("{}", text);
+            return Ok(());
+        }
+
+        let out = stdout();
+        let mut out = out.lock();
+        self.styled(&mut out, &text, Some(c), true)?;
+        writeln!(out)?;
+        out.flush()?;
+
+        Ok(())
+    }
+
+    fn styled<W: Write>(
+        &self,
+        out: &mut W,
+        text: &str,
+        c: Option<Color>,
+        bold: bool,
+    ) -> Result<()> {
+        match c {
+            Some(c) if self.color => {
+                queue!(out, SetForegroundColor(c))?;
+                if bold {
+                    queue!(out, SetAttribute(Attribute::Bold))?;
+                }
+                queue!(out, Print(text), ResetColor, SetAttribute(Attribute::Reset))?;
+            }
+            _ => write!(out, "{}", text)?,
+        }
+
+        Ok(())
+    }
+}
+
+fn format_timestamp(t: DateTime<Utc>) -> String {
+    t.to_rfc3339_opts(SecondsFormat::AutoSi, true)
+}
+
+/// Quotes a logfmt value when it is empty or contains spaces, quotes or `=`.
+fn logfmt_value(v: &str) -> String {
+    let plain = !v.is_empty()
+        && v.chars()
+            .all(|c| !c.is_whitespace() && !c.is_control() && c != '"' && c != '=');
+    if plain {
+        v.to_string()
+    } else {
+        format!("{:?}", v)
+    }
+}
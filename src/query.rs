@@ -0,0 +1,487 @@
+//! A small filter language for log lines.
+//!
+//! ```text
+//! expr    := or
+//! or      := and (("||" | "or") and)*
+//! and     := not (("&&" | "and")? not)*
+//! not     := ("!" | "not") not | atom
+//! atom    := "(" expr ")" | field op value | /regex/ | "text" | word
+//! op      := "==" | "=" | "!=" | ">" | ">=" | "<" | "<=" | "=~" | "!~"
+//! ```
+//!
+//! Bare words and quoted strings match anywhere in the raw line, `/regex/` is matched against
+//! the raw line, and comparisons are evaluated against the fields of JSON lines, e.g.
+//! `level>=warn && latency_ms>500 && !/healthz/`.
+//!
+//! Terms next to each other without an operator are and-ed, so `connection refused` matches
+//! lines containing both words; quote them to match the phrase. A `/` right after a comparison
+//! other than `=~` or `!~` is part of the value rather than the start of a regex, so
+//! `path=/api/v1` compares against the literal path.
+
+use anyhow::{anyhow, Result};
+use regex::Regex;
+use serde_json::{Map, Value};
+use std::cmp::Ordering;
+
+use crate::structured::{value_str, JsonFormat, Level};
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    LParen,
+    RParen,
+    And,
+    Or,
+    Not,
+    Op(Op),
+    Regex(String),
+    Str(String),
+    Word(String),
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Op {
+    Eq,
+    Ne,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+    Match,
+    NotMatch,
+}
+
+#[derive(Clone, Debug)]
+enum Operand {
+    Text(String),
+    Pattern(Regex),
+}
+
+#[derive(Clone, Debug)]
+enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Contains(String),
+    Pattern(Regex),
+    Compare(String, Op, Operand),
+}
+
+#[derive(Clone, Debug)]
+pub struct Query {
+    source: String,
+    expr: Expr,
+    json: JsonFormat,
+}
+
+impl Query {
+    pub fn parse(source: &str, json: JsonFormat) -> Result<Query> {
+        let tokens = tokenize(source)?;
+        let mut parser = Parser { tokens, pos: 0 };
+        let expr = parser.or()?;
+        if let Some(t) = parser.tokens.get(parser.pos) {
+            return Err(anyhow!("unexpected {:?} in query", t));
+        }
+
+        Ok(Query {
+            source: source.to_string(),
+            expr,
+            json,
+        })
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn matches(&self, line: &str) -> bool {
+        let obj = self.json.parse(line);
+        self.eval(&self.expr, line, obj.as_ref())
+    }
+
+    fn eval(&self, expr: &Expr, line: &str, obj: Option<&Map<String, Value>>) -> bool {
+        match expr {
+            Expr::And(a, b) => self.eval(a, line, obj) && self.eval(b, line, obj),
+            Expr::Or(a, b) => self.eval(a, line, obj) || self.eval(b, line, obj),
+            Expr::Not(e) => !self.eval(e, line, obj),
+            Expr::Contains(s) => line.contains(s.as_str()),
+            Expr::Pattern(re) => re.is_match(line),
+            Expr::Compare(field, op, operand) => {
+                let value = match obj.and_then(|o| self.json.field(o, field)) {
+                    Some(v) => v,
+                    None => return false,
+                };
+                compare(value, *op, operand, self.json.is_level_field(field))
+            }
+        }
+    }
+}
+
+/// Compares `value` against `operand`: by severity for level fields, numerically when both
+/// sides are numbers, and as strings otherwise.
+fn compare(value: &Value, op: Op, operand: &Operand, level: bool) -> bool {
+    let text = match operand {
+        Operand::Pattern(re) => {
+            let matched = re.is_match(&value_str(value));
+            return if op == Op::NotMatch {
+                !matched
+            } else {
+                matched
+            };
+        }
+        Operand::Text(t) => t,
+    };
+
+    let levels = if level {
+        Level::from_value(value).zip(Level::parse(text))
+    } else {
+        None
+    };
+    let ordering = match levels {
+        Some((a, b)) => Some(a.cmp(&b)),
+        None => {
+            let v = value_str(value);
+            match (v.parse::<f64>(), text.parse::<f64>()) {
+                (Ok(a), Ok(b)) => a.partial_cmp(&b),
+                _ => Some(v.as_str().cmp(text.as_str())),
+            }
+        }
+    };
+
+    match (op, ordering) {
+        (Op::Eq, Some(o)) => o == Ordering::Equal,
+        (Op::Ne, Some(o)) => o != Ordering::Equal,
+        (Op::Gt, Some(o)) => o == Ordering::Greater,
+        (Op::Ge, Some(o)) => o != Ordering::Less,
+        (Op::Lt, Some(o)) => o == Ordering::Less,
+        (Op::Le, Some(o)) => o != Ordering::Greater,
+        _ => false,
+    }
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = vec![];
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        let literal_value = match tokens.last() {
+            Some(Token::Op(op)) => *op != Op::Match && *op != Op::NotMatch,
+            _ => false,
+        };
+        let (token, len) = match (c, next) {
+            (c, _) if c.is_whitespace() => {
+                i += 1;
+                continue;
+            }
+            ('(', _) => (Token::LParen, 1),
+            (')', _) => (Token::RParen, 1),
+            ('&', Some('&')) => (Token::And, 2),
+            ('|', Some('|')) => (Token::Or, 2),
+            ('!', Some('=')) => (Token::Op(Op::Ne), 2),
+            ('!', Some('~')) => (Token::Op(Op::NotMatch), 2),
+            ('!', _) => (Token::Not, 1),
+            ('=', Some('=')) => (Token::Op(Op::Eq), 2),
+            ('=', Some('~')) => (Token::Op(Op::Match), 2),
+            ('=', _) => (Token::Op(Op::Eq), 1),
+            ('>', Some('=')) => (Token::Op(Op::Ge), 2),
+            ('>', _) => (Token::Op(Op::Gt), 1),
+            ('<', Some('=')) => (Token::Op(Op::Le), 2),
+            ('<', _) => (Token::Op(Op::Lt), 1),
+            ('/', _) if literal_value => word(&chars[i..]),
+            ('/', _) | ('"', _) | ('\'', _) => {
+                let (text, len) = delimited(&chars[i..])?;
+                let token = if c == '/' {
+                    Token::Regex(text)
+                } else {
+                    Token::Str(text)
+                };
+                (token, len)
+            }
+            _ => {
+                let (token, len) = word(&chars[i..]);
+                if len == 0 {
+                    return Err(anyhow!("unexpected '{}' in query", c));
+                }
+                (token, len)
+            }
+        };
+
+        tokens.push(token);
+        i += len;
+    }
+
+    Ok(tokens)
+}
+
+/// Reads a bare word, or one of the `and`, `or` and `not` keywords, starting at `chars[0]`.
+/// Returns the token and the number of characters consumed, which is 0 when there is no word.
+fn word(chars: &[char]) -> (Token, usize) {
+    let len = chars
+        .iter()
+        .take_while(|c| !c.is_whitespace() && !"()!=<>&|\"'".contains(**c))
+        .count();
+    let word: String = chars[..len].iter().collect();
+    let token = match word.to_lowercase().as_str() {
+        "and" => Token::And,
+        "or" => Token::Or,
+        "not" => Token::Not,
+        _ => Token::Word(word),
+    };
+
+    (token, len)
+}
+
+/// Reads a `/regex/` or quoted string starting at `chars[0]`, honouring backslash escapes of
+/// the delimiter. Returns the contents and the number of characters consumed.
+fn delimited(chars: &[char]) -> Result<(String, usize)> {
+    let delim = chars[0];
+    let mut text = String::new();
+    let mut i = 1;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' if chars.get(i + 1) == Some(&delim) => {
+                text.push(delim);
+                i += 2;
+            }
+            c if c == delim => return Ok((text, i + 1)),
+            c => {
+                text.push(c);
+                i += 1;
+            }
+        }
+    }
+
+    Err(anyhow!("unterminated {} in query", delim))
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let t = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        t
+    }
+
+    fn or(&mut self) -> Result<Expr> {
+        let mut expr = self.and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.pos += 1;
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+
+        Ok(expr)
+    }
+
+    /// Terms next to each other are and-ed as if joined by `&&`.
+    fn and(&mut self) -> Result<Expr> {
+        let mut expr = self.not()?;
+        loop {
+            match self.peek() {
+                Some(Token::And) => self.pos += 1,
+                Some(Token::LParen)
+                | Some(Token::Not)
+                | Some(Token::Word(_))
+                | Some(Token::Str(_))
+                | Some(Token::Regex(_)) => {}
+                _ => break,
+            }
+            expr = Expr::And(Box::new(expr), Box::new(self.not()?));
+        }
+
+        Ok(expr)
+    }
+
+    fn not(&mut self) -> Result<Expr> {
+        if self.peek() == Some(&Token::Not) {
+            self.pos += 1;
+            return Ok(Expr::Not(Box::new(self.not()?)));
+        }
+
+        self.atom()
+    }
+
+    fn atom(&mut self) -> Result<Expr> {
+        match self.next() {
+            Some(Token::LParen) => {
+                let expr = self.or()?;
+                match self.next() {
+                    Some(Token::RParen) => Ok(expr),
+                    _ => Err(anyhow!("expected ')' in query")),
+                }
+            }
+            Some(Token::Word(w)) => match self.peek() {
+                Some(Token::Op(op)) => {
+                    let op = *op;
+                    self.pos += 1;
+                    Ok(Expr::Compare(w, op, self.operand(op)?))
+                }
+                _ => Ok(Expr::Contains(w)),
+            },
+            Some(Token::Str(s)) => Ok(Expr::Contains(s)),
+            Some(Token::Regex(r)) => Ok(Expr::Pattern(Regex::new(&r)?)),
+            Some(t) => Err(anyhow!("unexpected {:?} in query", t)),
+            None => Err(anyhow!("unexpected end of query")),
+        }
+    }
+
+    fn operand(&mut self, op: Op) -> Result<Operand> {
+        let text = match self.next() {
+            Some(Token::Word(w)) | Some(Token::Str(w)) | Some(Token::Regex(w)) => w,
+            _ => return Err(anyhow!("expected a value after {:?}", op)),
+        };
+
+        match op {
+            Op::Match | Op::NotMatch => Ok(Operand::Pattern(Regex::new(&text)?)),
+            _ => Ok(Operand::Text(text)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::JsonOpts;
+
+    fn query(source: &str) -> Query {
+        let opts = JsonOpts {
+            json: false,
+            fields: None,
+            level_key: String::from("level,lvl,severity"),
+            message_key: String::from("msg,message"),
+            time_key: String::from("ts,time,timestamp,@timestamp"),
+        };
+        Query::parse(source, JsonFormat::from_keys(&opts)).unwrap()
+    }
+
+    fn parse_err(source: &str) -> String {
+        let opts = JsonOpts {
+            json: false,
+            fields: None,
+            level_key: String::from("level"),
+            message_key: String::from("msg"),
+            time_key: String::from("ts"),
+        };
+        Query::parse(source, JsonFormat::from_keys(&opts))
+            .unwrap_err()
+            .to_string()
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        let q = query("a || b && !c");
+        assert!(q.matches("a"));
+        assert!(q.matches("a c"));
+        assert!(q.matches("b"));
+        assert!(!q.matches("b c"));
+        assert!(!q.matches("c"));
+    }
+
+    #[test]
+    fn parentheses_group() {
+        let q = query("(a || b) && c");
+        assert!(q.matches("a c"));
+        assert!(q.matches("b c"));
+        assert!(!q.matches("a b"));
+        assert!(!q.matches("c"));
+
+        let q = query("!(a or b)");
+        assert!(q.matches("c"));
+        assert!(!q.matches("b"));
+    }
+
+    #[test]
+    fn adjacent_terms_are_anded() {
+        let q = query("connection refused");
+        assert!(q.matches("dial: connection refused"));
+        assert!(!q.matches("connection reset"));
+
+        let q = query("\"connection refused\"");
+        assert!(q.matches("connection refused"));
+        assert!(!q.matches("refused connection"));
+    }
+
+    #[test]
+    fn compares_levels_by_severity() {
+        let q = query("level>=warn");
+        assert!(q.matches(r#"{"level":"error","msg":"x"}"#));
+        assert!(q.matches(r#"{"level":"WARNING"}"#));
+        assert!(q.matches(r#"{"severity":50}"#));
+        assert!(!q.matches(r#"{"level":"info"}"#));
+        assert!(!q.matches("level=error but not json"));
+
+        let q = query("severity>=warn");
+        assert!(q.matches(r#"{"severity":"error"}"#));
+        assert!(!q.matches(r#"{"severity":"debug"}"#));
+    }
+
+    #[test]
+    fn compares_other_fields_as_text() {
+        let q = query("msg=err");
+        assert!(!q.matches(r#"{"msg":"error"}"#));
+        assert!(q.matches(r#"{"msg":"err"}"#));
+
+        let q = query("app==alert");
+        assert!(!q.matches(r#"{"app":"panic"}"#));
+
+        let q = query("code>40");
+        assert!(q.matches(r#"{"code":45}"#));
+        assert!(!q.matches(r#"{"code":35}"#));
+    }
+
+    #[test]
+    fn compares_numbers_numerically() {
+        let q = query("latency_ms>500");
+        assert!(q.matches(r#"{"latency_ms":750}"#));
+        assert!(q.matches(r#"{"latency_ms":"1200"}"#));
+        assert!(!q.matches(r#"{"latency_ms":90}"#));
+        assert!(!q.matches(r#"{"other":900}"#));
+
+        let q = query("status!=200");
+        assert!(q.matches(r#"{"status":503}"#));
+        assert!(!q.matches(r#"{"status":200}"#));
+    }
+
+    #[test]
+    fn matches_regex_operands() {
+        let q = query("msg=~/time(d)? ?out/");
+        assert!(q.matches(r#"{"msg":"request timed out"}"#));
+        assert!(q.matches(r#"{"message":"timeout"}"#));
+        assert!(!q.matches(r#"{"msg":"ok"}"#));
+
+        let q = query("msg!~\"^GET\"");
+        assert!(q.matches(r#"{"msg":"POST /"}"#));
+        assert!(!q.matches(r#"{"msg":"GET /"}"#));
+    }
+
+    #[test]
+    fn slash_after_comparison_is_a_value() {
+        let q = query("path=/api/v1");
+        assert!(q.matches(r#"{"path":"/api/v1"}"#));
+        assert!(!q.matches(r#"{"path":"/api/v2"}"#));
+
+        let q = query("/health(z)?/");
+        assert!(q.matches("GET /healthz"));
+    }
+
+    #[test]
+    fn reports_errors() {
+        assert!(parse_err("").contains("unexpected end"));
+        assert!(parse_err("(a").contains("expected ')'"));
+        assert!(parse_err("a )").contains("unexpected"));
+        assert!(parse_err("a &&").contains("unexpected end"));
+        assert!(parse_err("level>=").contains("expected a value"));
+        assert!(parse_err("/unterminated").contains("unterminated"));
+        assert!(parse_err("\"open").contains("unterminated"));
+        assert!(parse_err("msg=~/(/").contains("regex"));
+        assert!(parse_err("a & b").contains("unexpected '&'"));
+    }
+}
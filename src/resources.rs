@@ -0,0 +1,404 @@
+use k8s_openapi::api::{
+    apps::v1::{DaemonSet, Deployment, ReplicaSet, StatefulSet},
+    core::v1::{ConfigMap, Event, Node, Service},
+};
+use kube::api::{ListParams, Meta};
+use serde::de::DeserializeOwned;
+use std::collections::BTreeMap;
+use tokio::sync::mpsc;
+
+use crate::{
+    context::KubeContext,
+    util::{format_age, qualified_name},
+    watch::{watch_resources, ResourceEvent},
+};
+
+/// The kinds shown on the ui tabs next to pods.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ResourceKind {
+    Deployments,
+    ReplicaSets,
+    StatefulSets,
+    DaemonSets,
+    Services,
+    ConfigMaps,
+    Nodes,
+    Events,
+}
+
+pub const RESOURCE_KINDS: &[ResourceKind] = &[
+    ResourceKind::Deployments,
+    ResourceKind::ReplicaSets,
+    ResourceKind::StatefulSets,
+    ResourceKind::DaemonSets,
+    ResourceKind::Services,
+    ResourceKind::ConfigMaps,
+    ResourceKind::Nodes,
+    ResourceKind::Events,
+];
+
+/// One line of a resource table: the object it shows and its cells, in `headers` order.
+#[derive(Clone, Debug)]
+pub struct ResourceRow {
+    pub namespace: String,
+    pub name: String,
+    pub cells: Vec<String>,
+}
+
+/// The replica counts of a scalable workload.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Replicas {
+    pub desired: i32,
+    pub current: i32,
+    pub updated: i32,
+    pub ready: i32,
+}
+
+#[derive(Clone, Debug)]
+pub enum RowEvent {
+    Rows(Vec<ResourceRow>),
+    Error(String),
+}
+
+impl ResourceKind {
+    pub fn title(self) -> &'static str {
+        match self {
+            ResourceKind::Deployments => "Deployments",
+            ResourceKind::ReplicaSets => "ReplicaSets",
+            ResourceKind::StatefulSets => "StatefulSets",
+            ResourceKind::DaemonSets => "DaemonSets",
+            ResourceKind::Services => "Services",
+            ResourceKind::ConfigMaps => "ConfigMaps",
+            ResourceKind::Nodes => "Nodes",
+            ResourceKind::Events => "Events",
+        }
+    }
+
+    /// The letter that selects the tab; the first one unless another tab already has it.
+    pub fn key(self) -> char {
+        match self {
+            ResourceKind::Deployments => 'D',
+            ResourceKind::ReplicaSets => 'R',
+            ResourceKind::StatefulSets => 'S',
+            ResourceKind::DaemonSets => 'A',
+            ResourceKind::Services => 'V',
+            ResourceKind::ConfigMaps => 'C',
+            ResourceKind::Nodes => 'N',
+            ResourceKind::Events => 'E',
+        }
+    }
+
+    /// The kind to scale from this tab, for the workloads that can be scaled.
+    pub fn scalable(self) -> Option<&'static str> {
+        match self {
+            ResourceKind::Deployments => Some("Deployment"),
+            ResourceKind::StatefulSets => Some("StatefulSet"),
+            _ => None,
+        }
+    }
+
+    pub fn namespaced(self) -> bool {
+        self != ResourceKind::Nodes
+    }
+
+    pub fn headers(self) -> &'static [&'static str] {
+        match self {
+            ResourceKind::Deployments => &[
+                "Name",
+                "Desired",
+                "Current",
+                "Up-to-date",
+                "Ready",
+                "Available",
+                "Age",
+            ],
+            ResourceKind::ReplicaSets => &["Name", "Desired", "Current", "Ready", "Age"],
+            ResourceKind::StatefulSets => &["Name", "Desired", "Current", "Ready", "Age"],
+            ResourceKind::DaemonSets => &[
+                "Name",
+                "Desired",
+                "Current",
+                "Ready",
+                "Up-to-date",
+                "Available",
+                "Age",
+            ],
+            ResourceKind::Services => {
+                &["Name", "Type", "Cluster-IP", "External-IP", "Ports", "Age"]
+            }
+            ResourceKind::ConfigMaps => &["Name", "Data", "Age"],
+            ResourceKind::Nodes => &["Name", "Status", "Roles", "Age", "Version"],
+            ResourceKind::Events => &["Last Seen", "Type", "Reason", "Object", "Message"],
+        }
+    }
+}
+
+/// Keeps a table of `kind` in sync with a watch, sending every row on each change. Nodes are
+/// listed regardless of `namespace`. The task ends once the receiving side of `tx` is dropped.
+pub fn watch_rows(
+    ctx: &KubeContext,
+    kind: ResourceKind,
+    namespace: Option<&str>,
+    tx: mpsc::Sender<RowEvent>,
+) {
+    match kind {
+        ResourceKind::Deployments => watch_table(ctx, namespace, deployment_row, tx),
+        ResourceKind::ReplicaSets => watch_table(ctx, namespace, replica_set_row, tx),
+        ResourceKind::StatefulSets => watch_table(ctx, namespace, stateful_set_row, tx),
+        ResourceKind::DaemonSets => watch_table(ctx, namespace, daemon_set_row, tx),
+        ResourceKind::Services => watch_table(ctx, namespace, service_row, tx),
+        ResourceKind::ConfigMaps => watch_table(ctx, namespace, config_map_row, tx),
+        ResourceKind::Nodes => watch_table(ctx, None, node_row, tx),
+        ResourceKind::Events => watch_table(ctx, namespace, event_row, tx),
+    }
+}
+
+fn watch_table<K>(
+    ctx: &KubeContext,
+    namespace: Option<&str>,
+    row: fn(&K) -> Vec<String>,
+    mut tx: mpsc::Sender<RowEvent>,
+) where
+    K: k8s_openapi::Resource + Clone + DeserializeOwned + Meta + Send + 'static,
+{
+    let (obj_tx, mut obj_rx) = mpsc::channel(100);
+    watch_resources(ctx, ctx.api_in(namespace), ListParams::default(), obj_tx);
+    tokio::spawn(async move {
+        let mut cache: BTreeMap<String, K> = BTreeMap::new();
+        while let Some(event) = obj_rx.recv().await {
+            let event = match event {
+                ResourceEvent::Restarted(objs) => {
+                    cache = objs.into_iter().map(|o| (qualified_name(&o), o)).collect();
+                    None
+                }
+                ResourceEvent::Applied(o) => {
+                    cache.insert(qualified_name(&o), o);
+                    None
+                }
+                ResourceEvent::Deleted(o) => {
+                    cache.remove(&qualified_name(&o));
+                    None
+                }
+                ResourceEvent::Synced => None,
+                ResourceEvent::Error(e) => Some(RowEvent::Error(e)),
+            };
+
+            let event = event.unwrap_or_else(|| {
+                let rows = cache
+                    .values()
+                    .map(|o| ResourceRow {
+                        namespace: Meta::namespace(o).unwrap_or_default(),
+                        name: Meta::name(o),
+                        cells: row(o),
+                    })
+                    .collect();
+                RowEvent::Rows(rows)
+            });
+            if tx.send(event).await.is_err() {
+                break;
+            }
+        }
+    });
+}
+
+fn age<K: Meta>(obj: &K) -> String {
+    obj.meta()
+        .creation_timestamp
+        .as_ref()
+        .map(|t| format_age(&t.0))
+        .unwrap_or_else(|| String::from("<unknown>"))
+}
+
+pub fn deployment_replicas(d: &Deployment) -> Replicas {
+    let status = d.status.clone().unwrap_or_default();
+    Replicas {
+        desired: d.spec.as_ref().and_then(|s| s.replicas).unwrap_or(1),
+        current: status.replicas.unwrap_or(0),
+        updated: status.updated_replicas.unwrap_or(0),
+        ready: status.ready_replicas.unwrap_or(0),
+    }
+}
+
+pub fn stateful_set_replicas(s: &StatefulSet) -> Replicas {
+    let status = s.status.clone().unwrap_or_default();
+    Replicas {
+        desired: s.spec.as_ref().and_then(|s| s.replicas).unwrap_or(1),
+        current: status.replicas,
+        updated: status.updated_replicas.unwrap_or(0),
+        ready: status.ready_replicas.unwrap_or(0),
+    }
+}
+
+fn deployment_row(d: &Deployment) -> Vec<String> {
+    let r = deployment_replicas(d);
+    let available = d
+        .status
+        .as_ref()
+        .and_then(|s| s.available_replicas)
+        .unwrap_or(0);
+    vec![
+        Meta::name(d),
+        r.desired.to_string(),
+        r.current.to_string(),
+        r.updated.to_string(),
+        r.ready.to_string(),
+        available.to_string(),
+        age(d),
+    ]
+}
+
+fn replica_set_row(rs: &ReplicaSet) -> Vec<String> {
+    let desired = rs.spec.as_ref().and_then(|s| s.replicas).unwrap_or(1);
+    let status = rs.status.clone().unwrap_or_default();
+    vec![
+        Meta::name(rs),
+        desired.to_string(),
+        status.replicas.to_string(),
+        status.ready_replicas.unwrap_or(0).to_string(),
+        age(rs),
+    ]
+}
+
+fn stateful_set_row(s: &StatefulSet) -> Vec<String> {
+    let r = stateful_set_replicas(s);
+    vec![
+        Meta::name(s),
+        r.desired.to_string(),
+        r.current.to_string(),
+        r.ready.to_string(),
+        age(s),
+    ]
+}
+
+fn daemon_set_row(ds: &DaemonSet) -> Vec<String> {
+    let status = ds.status.clone().unwrap_or_default();
+    vec![
+        Meta::name(ds),
+        status.desired_number_scheduled.to_string(),
+        status.current_number_scheduled.to_string(),
+        status.number_ready.to_string(),
+        status.updated_number_scheduled.unwrap_or(0).to_string(),
+        status.number_available.unwrap_or(0).to_string(),
+        age(ds),
+    ]
+}
+
+fn service_row(svc: &Service) -> Vec<String> {
+    let spec = svc.spec.clone().unwrap_or_default();
+    let mut external: Vec<String> = svc
+        .status
+        .as_ref()
+        .and_then(|s| s.load_balancer.as_ref())
+        .and_then(|lb| lb.ingress.as_ref())
+        .map(|ingress| {
+            ingress
+                .iter()
+                .filter_map(|i| i.ip.clone().or_else(|| i.hostname.clone()))
+                .collect()
+        })
+        .unwrap_or_default();
+    external.extend(spec.external_ips.unwrap_or_default());
+    let ports: Vec<String> = spec
+        .ports
+        .unwrap_or_default()
+        .iter()
+        .map(|p| {
+            let protocol = p.protocol.as_deref().unwrap_or("TCP");
+            match p.node_port {
+                Some(n) => format!("{}:{}/{}", p.port, n, protocol),
+                None => format!("{}/{}", p.port, protocol),
+            }
+        })
+        .collect();
+
+    vec![
+        Meta::name(svc),
+        spec.type_.unwrap_or_else(|| String::from("ClusterIP")),
+        spec.cluster_ip.unwrap_or_default(),
+        if external.is_empty() {
+            String::from("<none>")
+        } else {
+            external.join(",")
+        },
+        ports.join(","),
+        age(svc),
+    ]
+}
+
+fn config_map_row(cm: &ConfigMap) -> Vec<String> {
+    let data =
+        cm.data.as_ref().map_or(0, |d| d.len()) + cm.binary_data.as_ref().map_or(0, |d| d.len());
+    vec![Meta::name(cm), data.to_string(), age(cm)]
+}
+
+fn node_row(node: &Node) -> Vec<String> {
+    let status = node.status.as_ref();
+    let ready = status
+        .and_then(|s| s.conditions.as_ref())
+        .and_then(|cs| cs.iter().find(|c| c.type_ == "Ready"))
+        .map_or("Unknown", |c| {
+            if c.status == "True" {
+                "Ready"
+            } else {
+                "NotReady"
+            }
+        });
+    let mut state = ready.to_string();
+    if node.spec.as_ref().and_then(|s| s.unschedulable) == Some(true) {
+        state.push_str(",SchedulingDisabled");
+    }
+
+    let roles: Vec<&str> = node
+        .metadata
+        .labels
+        .as_ref()
+        .map(|labels| {
+            labels
+                .keys()
+                .filter_map(|k| k.strip_prefix("node-role.kubernetes.io/"))
+                .collect()
+        })
+        .unwrap_or_default();
+    let version = status
+        .and_then(|s| s.node_info.as_ref())
+        .map(|i| i.kubelet_version.clone())
+        .unwrap_or_default();
+
+    vec![
+        Meta::name(node),
+        state,
+        if roles.is_empty() {
+            String::from("<none>")
+        } else {
+            roles.join(",")
+        },
+        age(node),
+        version,
+    ]
+}
+
+fn event_row(e: &Event) -> Vec<String> {
+    let seen = e
+        .last_timestamp
+        .as_ref()
+        .or_else(|| e.first_timestamp.as_ref())
+        .map(|t| format_age(&t.0))
+        .unwrap_or_else(|| String::from("<unknown>"));
+    let object = format!(
+        "{}/{}",
+        e.involved_object
+            .kind
+            .as_deref()
+            .unwrap_or("")
+            .to_lowercase(),
+        e.involved_object.name.as_deref().unwrap_or("")
+    );
+
+    vec![
+        seen,
+        e.type_.clone().unwrap_or_default(),
+        e.reason.clone().unwrap_or_default(),
+        object,
+        e.message.as_deref().unwrap_or("").trim().to_string(),
+    ]
+}
@@ -0,0 +1,117 @@
+use anyhow::Result;
+use flate2::{write::GzEncoder, Compression};
+use std::{
+    collections::HashMap,
+    fs::{self, File, OpenOptions},
+    io::{self, LineWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{logs::LogTarget, util::parse_size, LogsOpts};
+
+/// Writes each stream to its own plain text file under `--save-dir`, rotating files once they
+/// reach `--max-file-size` and keeping at most `--max-files` rotated segments per stream.
+pub struct LogSaver {
+    dir: PathBuf,
+    max_size: u64,
+    keep: usize,
+    compress: bool,
+    files: HashMap<String, LogFile>,
+}
+
+struct LogFile {
+    path: PathBuf,
+    out: LineWriter<File>,
+    size: u64,
+}
+
+impl LogSaver {
+    pub fn new(o: &LogsOpts) -> Result<Option<Self>> {
+        let dir = match &o.save_dir {
+            Some(d) => PathBuf::from(d),
+            None => return Ok(None),
+        };
+        fs::create_dir_all(&dir)?;
+
+        Ok(Some(LogSaver {
+            dir,
+            max_size: parse_size(&o.max_file_size)?,
+            keep: o.max_files,
+            compress: o.compress,
+            files: HashMap::new(),
+        }))
+    }
+
+    pub fn write(&mut self, target: &LogTarget, line: &str) -> Result<()> {
+        let name = file_name(target);
+        if !self.files.contains_key(&name) {
+            let file = LogFile::open(self.dir.join(format!("{}.log", name)))?;
+            self.files.insert(name.clone(), file);
+        }
+
+        let file = self.files.get_mut(&name).expect("log file was just opened");
+        if file.size > 0 && file.size + line.len() as u64 + 1 > self.max_size {
+            rotate(&file.path, self.keep, self.compress)?;
+            *file = LogFile::open(file.path.clone())?;
+        }
+
+        writeln!(file.out, "{}", line)?;
+        file.size += line.len() as u64 + 1;
+
+        Ok(())
+    }
+}
+
+impl LogFile {
+    fn open(path: PathBuf) -> Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let size = file.metadata()?.len();
+
+        Ok(LogFile {
+            path,
+            out: LineWriter::new(file),
+            size,
+        })
+    }
+}
+
+fn file_name(target: &LogTarget) -> String {
+    let mut name = format!("{}_{}", target.namespace, target.pod);
+    if let Some(c) = &target.container {
+        name.push('_');
+        name.push_str(c);
+    }
+
+    name.replace(|c: char| c == '/' || c == '\\', "_")
+}
+
+/// Shifts `app.log.1` to `app.log.2` and so on, dropping the oldest segment, then moves the
+/// active file to `app.log.1`, gzipping it to `app.log.1.gz` when `compress` is set.
+fn rotate(path: &Path, keep: usize, compress: bool) -> Result<()> {
+    let suffix = if compress { ".gz" } else { "" };
+    let segment = |n: usize| PathBuf::from(format!("{}.{}{}", path.display(), n, suffix));
+
+    if keep == 0 {
+        fs::remove_file(path)?;
+        return Ok(());
+    }
+
+    for n in (1..keep).rev() {
+        let from = segment(n);
+        if from.exists() {
+            fs::rename(&from, segment(n + 1))?;
+        }
+    }
+
+    if compress {
+        let mut input = File::open(path)?;
+        let mut output = GzEncoder::new(File::create(segment(1))?, Compression::default());
+        io::copy(&mut input, &mut output)?;
+        output.finish()?;
+        fs::remove_file(path)?;
+    } else {
+        fs::rename(path, segment(1))?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,103 @@
+use anyhow::{anyhow, Result};
+use k8s_openapi::api::apps::v1::{Deployment, StatefulSet};
+use kube::api::{ListParams, Meta};
+use serde::de::DeserializeOwned;
+use std::time::Duration;
+use tokio::{sync::mpsc, time::timeout};
+
+use crate::{
+    context::KubeContext,
+    logs::prompt,
+    resources::{deployment_replicas, stateful_set_replicas, Replicas},
+    util::scale_workload,
+    watch::{watch_resources, ResourceEvent},
+    ScaleOpts,
+};
+
+pub async fn scale(ctx: &KubeContext, o: &ScaleOpts) -> Result<()> {
+    let kind = match o.kind.as_str() {
+        "statefulset" => "StatefulSet",
+        _ => "Deployment",
+    };
+    if o.replicas < 0 {
+        return Err(anyhow!("replicas cannot be negative"));
+    }
+
+    if o.replicas == 0 && !o.yes {
+        let answer = prompt(&format!(
+            "Scale {}/{} in {} to zero, stopping every pod? [y/N] ",
+            kind, o.name, o.namespace
+        ))?;
+        if !answer.eq_ignore_ascii_case("y") && !answer.eq_ignore_ascii_case("yes") {
+            return Err(anyhow!("scale to zero not confirmed; pass --yes"));
+        }
+    }
+
+    scale_workload(ctx, &o.namespace, kind, &o.name, o.replicas).await?;
+    println!("Scaled {}/{} to {} replicas", kind, o.name, o.replicas);
+    if o.no_wait {
+        return Ok(());
+    }
+
+    match kind {
+        "StatefulSet" => follow_rollout(ctx, o, stateful_set_replicas).await,
+        _ => follow_rollout(ctx, o, deployment_replicas).await,
+    }
+}
+
+/// Prints the replica counts of the workload every time they change, until the current and
+/// ready counts reach `--replicas` or `--timeout` runs out. Scaling does not start a new
+/// rollout, so the up-to-date count is not waited on.
+async fn follow_rollout<K>(
+    ctx: &KubeContext,
+    o: &ScaleOpts,
+    replicas: fn(&K) -> Replicas,
+) -> Result<()>
+where
+    K: k8s_openapi::Resource + Clone + DeserializeOwned + Meta + Send + 'static,
+{
+    let (tx, mut rx) = mpsc::channel(10);
+    let lp = ListParams::default().fields(&format!("metadata.name={}", o.name));
+    watch_resources(ctx, ctx.api_in(Some(&o.namespace)), lp, tx);
+
+    let follow = async {
+        let mut last = None;
+        while let Some(event) = rx.recv().await {
+            let obj = match event {
+                ResourceEvent::Restarted(objs) => objs.into_iter().next(),
+                ResourceEvent::Applied(obj) => Some(obj),
+                ResourceEvent::Deleted(_) => return Err(anyhow!("{} was deleted", o.name)),
+                ResourceEvent::Synced => None,
+                ResourceEvent::Error(e) => {
+                    eprintln!("Watch error: {}", e);
+                    None
+                }
+            };
+            let r = match obj {
+                Some(obj) => replicas(&obj),
+                None => continue,
+            };
+
+            if last != Some(r) {
+                println!(
+                    "desired: {}  current: {}  up-to-date: {}  ready: {}",
+                    r.desired, r.current, r.updated, r.ready
+                );
+                last = Some(r);
+            }
+            if r.current == o.replicas && r.ready == o.replicas {
+                println!("Scale complete");
+                return Ok(());
+            }
+        }
+
+        Ok(())
+    };
+
+    match o.timeout {
+        0 => follow.await,
+        secs => timeout(Duration::from_secs(secs), follow)
+            .await
+            .map_err(|_| anyhow!("timed out after {}s waiting for the rollout", secs))?,
+    }
+}
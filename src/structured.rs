@@ -0,0 +1,205 @@
+use serde_json::{Map, Value};
+
+use crate::JsonOpts;
+
+const LEVEL_ALIASES: &[&str] = &["level", "lvl"];
+const MESSAGE_ALIASES: &[&str] = &["msg", "message"];
+const TIME_ALIASES: &[&str] = &["ts", "time", "timestamp"];
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+    Fatal,
+}
+
+impl Level {
+    pub fn parse(s: &str) -> Option<Level> {
+        match s.trim().to_lowercase().as_str() {
+            "trace" => Some(Level::Trace),
+            "debug" | "dbg" => Some(Level::Debug),
+            "info" | "information" | "notice" => Some(Level::Info),
+            "warn" | "warning" => Some(Level::Warn),
+            "error" | "err" => Some(Level::Error),
+            "fatal" | "critical" | "crit" | "panic" | "alert" | "emerg" => Some(Level::Fatal),
+            _ => None,
+        }
+    }
+
+    /// Accepts both level names and the numeric levels used by bunyan and pino.
+    pub fn from_value(v: &Value) -> Option<Level> {
+        match v {
+            Value::String(s) => Level::parse(s),
+            Value::Number(n) => match n.as_u64()? {
+                0..=10 => Some(Level::Trace),
+                11..=20 => Some(Level::Debug),
+                21..=30 => Some(Level::Info),
+                31..=40 => Some(Level::Warn),
+                41..=50 => Some(Level::Error),
+                _ => Some(Level::Fatal),
+            },
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Level::Trace => "TRACE",
+            Level::Debug => "DEBUG",
+            Level::Info => "INFO",
+            Level::Warn => "WARN",
+            Level::Error => "ERROR",
+            Level::Fatal => "FATAL",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct JsonFormat {
+    level_keys: Vec<String>,
+    message_keys: Vec<String>,
+    time_keys: Vec<String>,
+    fields: Vec<String>,
+}
+
+impl JsonFormat {
+    /// Returns a format only when JSON rendering was asked for with `--json` or `--fields`.
+    pub fn new(o: &JsonOpts) -> Option<Self> {
+        if !o.json && o.fields.is_none() {
+            return None;
+        }
+
+        Some(JsonFormat::from_keys(o))
+    }
+
+    pub fn from_keys(o: &JsonOpts) -> Self {
+        JsonFormat {
+            level_keys: split_keys(&o.level_key),
+            message_keys: split_keys(&o.message_key),
+            time_keys: split_keys(&o.time_key),
+            fields: o.fields.as_deref().map(split_keys).unwrap_or_default(),
+        }
+    }
+
+    pub fn parse(&self, line: &str) -> Option<Map<String, Value>> {
+        if !line.trim_start().starts_with('{') {
+            return None;
+        }
+
+        match serde_json::from_str(line) {
+            Ok(Value::Object(obj)) => Some(obj),
+            _ => None,
+        }
+    }
+
+    pub fn level(&self, obj: &Map<String, Value>) -> Option<Level> {
+        find_key(obj, &self.level_keys).and_then(|(_, v)| Level::from_value(v))
+    }
+
+    /// Whether `name` is the `level` alias or one of the `--level-key` keys.
+    pub fn is_level_field(&self, name: &str) -> bool {
+        LEVEL_ALIASES.contains(&name) || self.level_keys.iter().any(|k| k == name)
+    }
+
+    /// Looks up `name` in `obj`, following dotted paths into nested objects and falling back
+    /// to the configured keys for the `level`, `msg` and `ts` aliases.
+    pub fn field<'a>(&self, obj: &'a Map<String, Value>, name: &str) -> Option<&'a Value> {
+        if let Some(v) = lookup(obj, name) {
+            return Some(v);
+        }
+
+        let keys = if LEVEL_ALIASES.contains(&name) {
+            &self.level_keys
+        } else if MESSAGE_ALIASES.contains(&name) {
+            &self.message_keys
+        } else if TIME_ALIASES.contains(&name) {
+            &self.time_keys
+        } else {
+            return None;
+        };
+
+        find_key(obj, keys).map(|(_, v)| v)
+    }
+
+    pub fn render(&self, obj: &Map<String, Value>) -> String {
+        if !self.fields.is_empty() {
+            return self
+                .fields
+                .iter()
+                .map(|f| {
+                    let v = self.field(obj, f);
+                    if LEVEL_ALIASES.contains(&f.as_str()) {
+                        let level = v.and_then(Level::from_value).map(Level::as_str);
+                        format!("{:<5}", level.unwrap_or("-"))
+                    } else {
+                        v.map(value_str).unwrap_or_else(|| String::from("-"))
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join("  ");
+        }
+
+        let mut parts = vec![];
+        let mut used = vec![];
+        if let Some((k, v)) = find_key(obj, &self.time_keys) {
+            parts.push(value_str(v));
+            used.push(k);
+        }
+        if let Some((k, v)) = find_key(obj, &self.level_keys) {
+            let level = Level::from_value(v).map(Level::as_str);
+            parts.push(format!("{:<5}", level.unwrap_or("-")));
+            used.push(k);
+        }
+        if let Some((k, v)) = find_key(obj, &self.message_keys) {
+            parts.push(value_str(v));
+            used.push(k);
+        }
+        for (k, v) in obj {
+            if !used.contains(&k.as_str()) {
+                parts.push(format!("{}={}", k, value_str(v)));
+            }
+        }
+
+        parts.join("  ")
+    }
+}
+
+fn split_keys(keys: &str) -> Vec<String> {
+    keys.split(',')
+        .map(|k| k.trim().to_string())
+        .filter(|k| !k.is_empty())
+        .collect()
+}
+
+fn find_key<'a, 'k>(
+    obj: &'a Map<String, Value>,
+    keys: &'k [String],
+) -> Option<(&'k str, &'a Value)> {
+    keys.iter()
+        .find_map(|k| obj.get(k.as_str()).map(|v| (k.as_str(), v)))
+}
+
+fn lookup<'a>(obj: &'a Map<String, Value>, path: &str) -> Option<&'a Value> {
+    if let Some(v) = obj.get(path) {
+        return Some(v);
+    }
+
+    let mut parts = path.split('.');
+    let mut current = obj.get(parts.next()?)?;
+    for part in parts {
+        current = current.as_object()?.get(part)?;
+    }
+
+    Some(current)
+}
+
+pub fn value_str(v: &Value) -> String {
+    match v {
+        Value::String(s) => s.clone(),
+        Value::Null => String::from("-"),
+        other => other.to_string(),
+    }
+}
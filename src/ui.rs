@@ -1,27 +1,51 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
 use crossterm::{
     event::{self, Event as CEvent, KeyCode, KeyEvent},
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
     ExecutableCommand,
 };
+use futures::future::{abortable, AbortHandle};
 use k8s_openapi::api::core::v1::Pod;
-use kube::api::Meta;
-use std::{io, time::Instant};
+use kube::api::{ListParams, Meta};
+use std::{
+    collections::{BTreeMap, VecDeque},
+    io,
+    time::Instant,
+};
 use std::{panic, time::Duration};
 use tui::{
     backend::CrosstermBackend,
-    layout::{Alignment, Constraint, Direction, Layout},
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
     text::{Span, Spans},
-    widgets::{Block, BorderType, Borders, Cell, Paragraph, Row, Table, TableState, Tabs},
+    widgets::{
+        Block, BorderType, Borders, Cell, Clear, List, ListItem, ListState, Paragraph, Row, Table,
+        TableState, Tabs, Wrap,
+    },
     Terminal,
 };
 
 use crate::{
-    util::{describe_pod, get_context, get_pods},
+    context::{contexts, KubeContext},
+    describe::{pod_controller, pod_status},
+    logs::{follow_params, pod_containers, read_log_lines, LineFormatter, LineKind, LogEvent},
+    query::Query,
+    resources::{watch_rows, ResourceKind, ResourceRow, RowEvent, RESOURCE_KINDS},
+    structured::JsonFormat,
+    util::{
+        crash_report, delete_pod, describe_pod, format_age, pod_list_params, qualified_name,
+        restart_owner, scale_workload, OptionEx,
+    },
+    watch::{watch_pods, PodEvent},
     UIOpts,
 };
 
+const LOG_SCROLLBACK: usize = 5000;
+const MAX_COLUMN_WIDTH: u16 = 60;
+const ALL_NAMESPACES: &str = "all namespaces";
+const TOAST_DURATION: Duration = Duration::from_secs(5);
+
 #[derive(Clone, Debug)]
 pub enum Event<I> {
     Input(I),
@@ -31,28 +55,170 @@ pub enum Event<I> {
 #[derive(Copy, Clone, Debug)]
 enum ActionItem {
     Home,
+    Describe,
+    CrashReport,
+    Logs,
+    Resources(ResourceKind),
 }
 
 impl From<ActionItem> for usize {
     fn from(input: ActionItem) -> usize {
         match input {
-            ActionItem::Home => 0,
+            ActionItem::Home
+            | ActionItem::Describe
+            | ActionItem::CrashReport
+            | ActionItem::Logs => 0,
+            ActionItem::Resources(kind) => {
+                RESOURCE_KINDS.iter().position(|k| *k == kind).unwrap_or(0) + 1
+            }
         }
     }
 }
 
 #[derive(Clone, Debug)]
 pub struct KubePod {
+    namespace: String,
     name: String,
+    containers: Vec<String>,
     ready: i32,
     container_count: i32,
     status: String,
     restart_count: i32,
+    created: Option<DateTime<Utc>>,
+    node: String,
+    ip: String,
+    owner: Option<(String, String)>,
+    qos: String,
+    labels: Vec<String>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum PodSort {
+    Name,
+    Age,
+    Restarts,
+    Status,
+}
+
+/// The pods from the watch, narrowed by the `/` filter and ordered by the chosen column.
+struct PodTable {
+    pods: Vec<KubePod>,
+    /// Indices into `pods` that pass the filter, in display order.
+    visible: Vec<usize>,
+    state: TableState,
+    filter: String,
+    editing: bool,
+    sort: Option<PodSort>,
+    descending: bool,
 }
 
 #[derive(Clone, Debug)]
 pub enum UIEvent {
-    RefreshPods(Vec<KubePod>),
+    RefreshPods(usize, Vec<KubePod>),
+    WatchError(usize, String),
+    Choices(PickKind, Vec<String>),
+    Message(String),
+    Describe(String),
+    CrashReport(String),
+    LogLine(usize, LogEvent),
+    Resources(usize, RowEvent),
+    /// The outcome of a confirmed action: what was done, or why it failed.
+    ActionDone(Result<String, String>),
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum PickKind {
+    Namespace,
+    Context,
+}
+
+enum Command {
+    /// Switch to a namespace, or to all namespaces with `None`.
+    Namespace(Option<String>),
+    Context(String),
+    Pick(PickKind),
+}
+
+/// The `:` command line, and the list opened by `:ns` or `:ctx` without an argument.
+enum Prompt {
+    Input(String),
+    Pick {
+        kind: PickKind,
+        items: Vec<String>,
+        state: ListState,
+    },
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Action {
+    Delete,
+    ForceDelete,
+    Restart,
+    /// Scale the selected workload, a Deployment or StatefulSet.
+    Scale(&'static str),
+}
+
+/// The dialog asking whether to go ahead with an action on the selected pod or workload.
+struct Confirm {
+    action: Action,
+    namespace: String,
+    name: String,
+    owner: Option<(String, String)>,
+    /// The grace period typed for a delete, where empty means the pod's own, or the replicas
+    /// typed for a scale.
+    input: String,
+    /// Set once scaling to zero has been confirmed the first time.
+    armed: bool,
+}
+
+/// A result shown in the status line for a few seconds.
+struct Toast {
+    text: String,
+    error: bool,
+    shown: Instant,
+}
+
+struct LogPane {
+    namespace: String,
+    pod: String,
+    containers: Vec<String>,
+    container: usize,
+    /// Tags the lines of the current stream, so those still queued from an earlier one are
+    /// dropped.
+    stream_id: usize,
+    stream_name: String,
+    lines: VecDeque<PaneLine>,
+    /// Lines held back while paused, capped like the scrollback.
+    pending: VecDeque<PaneLine>,
+    /// How many lines arrived while paused, including those dropped from `pending`.
+    pending_count: usize,
+    formatter: LineFormatter,
+    json: JsonFormat,
+    query: Option<Query>,
+    input: Option<String>,
+    tail_length: i64,
+    offset: usize,
+    paused: bool,
+    wrap: bool,
+    previous: bool,
+    stream: Option<AbortHandle>,
+}
+
+/// The table shown on every tab other than pods, fed by a watch on the tab's kind.
+struct ResourceTable {
+    kind: ResourceKind,
+    rows: Vec<ResourceRow>,
+    state: TableState,
+    error: Option<String>,
+    id: usize,
+    watch: Option<AbortHandle>,
+}
+
+struct PaneLine {
+    kind: LineKind,
+    text: String,
+    raw: String,
+    visible: bool,
 }
 
 // #[derive(Clone, Debug)]
@@ -93,7 +259,15 @@ fn start_key_events() -> tokio::sync::mpsc::Receiver<Event<KeyEvent>> {
     rx
 }
 
-pub async fn load_ui(namespace: &str, _opts: &UIOpts) -> Result<()> {
+pub async fn load_ui(ctx: &KubeContext, namespace: &str, opts: &UIOpts) -> Result<()> {
+    // Anything that can fail on bad options is built before the terminal is taken over, so
+    // the error is not left behind in raw mode on the alternate screen.
+    let mut log_pane = LogPane::new(
+        LineFormatter::new(&opts.highlight.to_str(), &opts.json, None)?,
+        JsonFormat::from_keys(&opts.json),
+        opts.tail_length,
+    );
+
     println!("Loading UI...");
     enable_raw_mode().expect("can run in raw mode");
 
@@ -110,15 +284,37 @@ pub async fn load_ui(namespace: &str, _opts: &UIOpts) -> Result<()> {
     io::stdout().execute(EnterAlternateScreen)?;
     terminal.clear()?;
 
-    let menu_titles = vec!["Pods"];
+    let mut menu_titles = vec![("Pods", 'P')];
+    menu_titles.extend(RESOURCE_KINDS.iter().map(|k| (k.title(), k.key())));
     let mut active_action_item = ActionItem::Home;
-    let mut pod_table_state = TableState::default();
-    pod_table_state.select(Some(0));
+    let mut pod_table = PodTable::new();
 
-    let (mut ui_tx, mut ui_rx) = tokio::sync::mpsc::channel(1);
-    let mut pod_list = vec![];
-    refresh_pod_list(namespace, ui_tx.clone());
-    let cluster_url = get_context().await?;
+    let (mut ui_tx, mut ui_rx) = tokio::sync::mpsc::channel(100);
+    let mut describe_text = String::new();
+    let mut describe_scroll: u16 = 0;
+    let mut page_height: u16 = 0;
+    let mut resource_table = ResourceTable::new();
+    let mut last_synced: Option<DateTime<Utc>> = None;
+    let mut sync_error: Option<String> = None;
+    let mut prompt: Option<Prompt> = None;
+    let mut message: Option<String> = None;
+    let mut confirm: Option<Confirm> = None;
+    let mut toast: Option<Toast> = None;
+    let mut ctx = ctx.clone();
+    let mut namespace = if opts.all_namespaces {
+        None
+    } else {
+        Some(namespace.to_string())
+    };
+    let lp = pod_list_params(&opts.selector, &opts.field_selector);
+    let mut watch_id = 0;
+    let mut pod_watch = watch_pod_list(
+        &ctx,
+        namespace.as_deref(),
+        lp.clone(),
+        watch_id,
+        ui_tx.clone(),
+    );
 
     loop {
         terminal.draw(|rect| {
@@ -136,24 +332,37 @@ pub async fn load_ui(namespace: &str, _opts: &UIOpts) -> Result<()> {
                 )
                 .split(size);
 
-            let cluster_context = Paragraph::new(cluster_url.to_string())
-                .style(Style::default().fg(Color::LightCyan))
-                .alignment(Alignment::Center)
-                .block(
-                    Block::default()
-                        .borders(Borders::ALL)
-                        .style(Style::default().fg(Color::White))
-                        .title("Context")
-                        .border_type(BorderType::Plain),
-                );
+            let status = match &prompt {
+                Some(Prompt::Input(input)) => Spans::from(format!(":{}_", input)),
+                _ => render_status(
+                    ctx.name(),
+                    namespace.as_deref().unwrap_or(ALL_NAMESPACES),
+                    last_synced.as_ref(),
+                    sync_error.as_deref(),
+                    message.as_deref(),
+                    toast.as_ref(),
+                ),
+            };
+            let cluster_context = Paragraph::new(status).alignment(Alignment::Center).block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .style(Style::default().fg(Color::White))
+                    .title("Status")
+                    .border_type(BorderType::Plain),
+            );
 
             let menu = menu_titles
                 .iter()
-                .map(|t| {
-                    let (first, rest) = t.split_at(1);
+                .map(|(t, key)| {
+                    let at = t
+                        .find(|c: char| c.to_ascii_uppercase() == *key)
+                        .unwrap_or(0);
+                    let (before, rest) = t.split_at(at);
+                    let (key, rest) = rest.split_at(1);
                     Spans::from(vec![
+                        Span::styled(before, Style::default().fg(Color::White)),
                         Span::styled(
-                            first,
+                            key,
                             Style::default()
                                 .fg(Color::Yellow)
                                 .add_modifier(Modifier::UNDERLINED),
@@ -173,17 +382,84 @@ pub async fn load_ui(namespace: &str, _opts: &UIOpts) -> Result<()> {
             rect.render_widget(tabs, chunks[0]);
             match active_action_item {
                 ActionItem::Home => {
-                    let table = render_pods(&pod_list);
-                    rect.render_stateful_widget(table, chunks[1], &mut pod_table_state);
+                    let widths = pod_table.widths(namespace.is_none());
+                    let table = pod_table.render(namespace.is_none(), &widths);
+                    rect.render_stateful_widget(table, chunks[1], &mut pod_table.state);
+                }
+                ActionItem::Describe | ActionItem::CrashReport => {
+                    page_height = chunks[1].height.saturating_sub(2);
+                    let title = match active_action_item {
+                        ActionItem::CrashReport => "Crash Report",
+                        _ => "Describe",
+                    };
+                    let describe = Paragraph::new(describe_text.as_str())
+                        .style(Style::default().fg(Color::White))
+                        .scroll((describe_scroll, 0))
+                        .block(
+                            Block::default()
+                                .borders(Borders::ALL)
+                                .style(Style::default().fg(Color::White))
+                                .title(title)
+                                .border_type(BorderType::Plain),
+                        );
+                    rect.render_widget(describe, chunks[1]);
                 }
+                ActionItem::Logs => {
+                    page_height = chunks[1].height.saturating_sub(2);
+                    rect.render_widget(log_pane.render(chunks[1]), chunks[1]);
+                }
+                ActionItem::Resources(_) => {
+                    let widths = resource_table.widths(namespace.is_none());
+                    let table = resource_table.render(namespace.is_none(), &widths);
+                    rect.render_stateful_widget(table, chunks[1], &mut resource_table.state);
+                }
+            }
+            if let Some(Prompt::Pick { kind, items, state }) = &mut prompt {
+                let area = popup_area(chunks[1]);
+                rect.render_widget(Clear, area);
+                rect.render_stateful_widget(render_picker(*kind, items), area, state);
+            }
+            if let Some(c) = &confirm {
+                let area = popup_area(chunks[1]);
+                rect.render_widget(Clear, area);
+                rect.render_widget(c.render(), area);
             }
             rect.render_widget(cluster_context, chunks[2]);
         })?;
 
+        let mut command = None;
         tokio::select! {
         Some(event) = rx.recv() =>{
                match event {
-                   Event::Input(event) => match event.code {
+                   Event::Input(event) if log_pane.editing() => log_pane.edit(event.code),
+                   Event::Input(event) if pod_table.editing => pod_table.edit(event.code),
+                   Event::Input(event) if confirm.is_some() => {
+                       if let Some(c) = handle_confirm(&mut confirm, event.code) {
+                           run_action(&ctx, c, ui_tx.clone());
+                       }
+                   }
+                   Event::Input(event) if prompt.is_some() => {
+                       match handle_prompt(&mut prompt, event.code) {
+                           Ok(c) => command = c,
+                           Err(e) => message = Some(e),
+                       }
+                   }
+                   Event::Input(KeyEvent { code: KeyCode::Char(c), .. })
+                       if is_table(active_action_item) && is_tab_key(c) =>
+                   {
+                       match tab_for_key(c) {
+                           Some(ActionItem::Resources(kind)) => {
+                               let n = namespace.as_deref();
+                               resource_table.open(&ctx, kind, n, ui_tx.clone());
+                               active_action_item = ActionItem::Resources(kind);
+                           }
+                           _ => {
+                               resource_table.close();
+                               active_action_item = ActionItem::Home;
+                           }
+                       }
+                   }
+                   Event::Input(event) => match opts.keys.translate(event.code) {
                        KeyCode::Char('q') => {
                            disable_raw_mode()?;
                            io::stdout().execute(LeaveAlternateScreen)?;
@@ -191,39 +467,269 @@ pub async fn load_ui(namespace: &str, _opts: &UIOpts) -> Result<()> {
                            break;
                        }
                        KeyCode::Down | KeyCode::Char('j') => match active_action_item {
-                           ActionItem::Home => {
-                               if let Some(selected) = pod_table_state.selected() {
-                                   if selected >= pod_list.len() - 1 {
-                                       pod_table_state.select(Some(0));
-                                   } else {
-                                       pod_table_state.select(Some(selected + 1));
-                                   }
-                               }
+                           ActionItem::Home => pod_table.next(),
+                           ActionItem::Describe | ActionItem::CrashReport => {
+                               let next = describe_scroll.saturating_add(1);
+                               describe_scroll = max_scroll(&describe_text, next);
                            }
+                           ActionItem::Logs => log_pane.scroll_down(1),
+                           ActionItem::Resources(_) => resource_table.next(),
                        },
                        KeyCode::Up | KeyCode::Char('k') => match active_action_item {
-                           ActionItem::Home => {
-                               if let Some(selected) = pod_table_state.selected() {
-                                   if selected > 0 {
-                                       pod_table_state.select(Some(selected - 1));
-                                   } else {
-                                       pod_table_state.select(Some(pod_list.len() - 1));
+                           ActionItem::Home => pod_table.previous(),
+                           ActionItem::Describe | ActionItem::CrashReport => {
+                               describe_scroll = describe_scroll.saturating_sub(1);
+                           }
+                           ActionItem::Logs => log_pane.scroll_up(1),
+                           ActionItem::Resources(_) => resource_table.previous(),
+                       },
+                       KeyCode::PageDown => match active_action_item {
+                           ActionItem::Describe | ActionItem::CrashReport => {
+                               let next = describe_scroll.saturating_add(page_height);
+                               describe_scroll = max_scroll(&describe_text, next);
+                           }
+                           ActionItem::Logs => log_pane.scroll_down(page_height as usize),
+                           _ => {}
+                       },
+                       KeyCode::PageUp => match active_action_item {
+                           ActionItem::Describe | ActionItem::CrashReport => {
+                               describe_scroll = describe_scroll.saturating_sub(page_height);
+                           }
+                           ActionItem::Logs => log_pane.scroll_up(page_height as usize),
+                           _ => {}
+                       },
+                       KeyCode::End | KeyCode::Char('G') => {
+                           if let ActionItem::Logs = active_action_item {
+                               log_pane.offset = 0;
+                           }
+                       }
+                       KeyCode::Char('l') => {
+                           if let ActionItem::Home = active_action_item {
+                               if let Some(p) = pod_table.selected() {
+                                   log_pane.open(&ctx, &p.namespace, p, ui_tx.clone());
+                                   active_action_item = ActionItem::Logs;
+                               }
+                           }
+                       }
+                       KeyCode::Char('p') | KeyCode::Char(' ') => {
+                           if let ActionItem::Logs = active_action_item {
+                               log_pane.toggle_pause();
+                           }
+                       }
+                       KeyCode::Char('c') => {
+                           if let ActionItem::Logs = active_action_item {
+                               log_pane.next_container(&ctx, ui_tx.clone());
+                           }
+                       }
+                       KeyCode::Char('/') => match active_action_item {
+                           ActionItem::Home => pod_table.editing = true,
+                           ActionItem::Logs => log_pane.start_editing(),
+                           _ => {}
+                       },
+                       KeyCode::Char(c @ 'n')
+                       | KeyCode::Char(c @ 'a')
+                       | KeyCode::Char(c @ 'r')
+                       | KeyCode::Char(c @ 's') => {
+                           if let ActionItem::Home = active_action_item {
+                               pod_table.sort_by(match c {
+                                   'n' => PodSort::Name,
+                                   'a' => PodSort::Age,
+                                   'r' => PodSort::Restarts,
+                                   _ => PodSort::Status,
+                               });
+                           }
+                       }
+                       KeyCode::Char(c @ 'K') | KeyCode::Char(c @ 'F') | KeyCode::Char(c @ 'O') => {
+                           if let ActionItem::Home = active_action_item {
+                               if let Some(p) = pod_table.selected() {
+                                   let action = match c {
+                                       'K' => Action::Delete,
+                                       'F' => Action::ForceDelete,
+                                       _ => Action::Restart,
+                                   };
+                                   confirm = Some(Confirm::new(action, p));
+                               }
+                           }
+                       }
+                       KeyCode::Char('z') => {
+                           if let ActionItem::Resources(kind) = active_action_item {
+                               match (kind.scalable(), resource_table.selected()) {
+                                   (Some(k), Some(row)) => confirm = Some(Confirm::scale(k, row)),
+                                   (None, _) => {
+                                       toast = Some(Toast {
+                                           text: format!("{} cannot be scaled", kind.title()),
+                                           error: true,
+                                           shown: Instant::now(),
+                                       });
                                    }
+                                   _ => {}
+                               }
+                           }
+                       }
+                       KeyCode::Char(':') => {
+                           message = None;
+                           prompt = Some(Prompt::Input(String::new()));
+                       }
+                       KeyCode::Char('w') => {
+                           if let ActionItem::Logs = active_action_item {
+                               log_pane.wrap = !log_pane.wrap;
+                           }
+                       }
+                       KeyCode::Char('P') => {
+                           if let ActionItem::Logs = active_action_item {
+                               log_pane.toggle_previous(&ctx, ui_tx.clone());
+                           }
+                       }
+                       KeyCode::Char('x') => {
+                           if let ActionItem::Home = active_action_item {
+                               if let Some(p) = pod_table.selected() {
+                                   describe_text = format!("Loading crash report {}...", p.name);
+                                   describe_scroll = 0;
+                                   active_action_item = ActionItem::CrashReport;
+                                   let (ns, tail) = (&p.namespace, opts.tail_length);
+                                   load_crash_report(&ctx, ns, &p.name, tail, ui_tx.clone());
+                               }
+                           }
+                       }
+                       KeyCode::Char('d') => {
+                           if let ActionItem::Home = active_action_item {
+                               if let Some(p) = pod_table.selected() {
+                                   describe_text = format!("Describing {}...", p.name);
+                                   describe_scroll = 0;
+                                   active_action_item = ActionItem::Describe;
+                                   load_describe(&ctx, &p.namespace, &p.name, ui_tx.clone());
                                }
                            }
+                       }
+                       KeyCode::Esc => match active_action_item {
+                           ActionItem::Home => pod_table.set_filter(String::new()),
+                           ActionItem::Describe | ActionItem::CrashReport => {
+                               active_action_item = ActionItem::Home
+                           }
+                           ActionItem::Logs => {
+                               log_pane.close();
+                               active_action_item = ActionItem::Home;
+                           }
+                           ActionItem::Resources(_) => {
+                               resource_table.close();
+                               active_action_item = ActionItem::Home;
+                           }
                        },
                        _ => {}
                    },
-                   Event::Tick => {}
+                   Event::Tick => {
+                       if toast.as_ref().map_or(false, |t| t.shown.elapsed() >= TOAST_DURATION) {
+                           toast = None;
+                       }
+                   }
                }
         }
             Some(ui_event) = ui_rx.recv() => {
                 match ui_event {
-                    UIEvent::RefreshPods(pods) => pod_list = pods
+                    UIEvent::RefreshPods(id, _) | UIEvent::WatchError(id, _) if id != watch_id => {}
+                    UIEvent::RefreshPods(_, pods) => {
+                        pod_table.set_pods(pods);
+                        last_synced = Some(Utc::now());
+                        sync_error = None;
+                    }
+                    UIEvent::WatchError(_, e) => sync_error = Some(e),
+                    UIEvent::Choices(kind, choices) => {
+                        if let Some(Prompt::Pick { kind: k, items, .. }) = &mut prompt {
+                            if *k == kind {
+                                *items = choices;
+                            }
+                        }
+                    }
+                    UIEvent::Message(m) => {
+                        if let Some(Prompt::Pick { .. }) = prompt {
+                            prompt = None;
+                        }
+                        message = Some(m);
+                    }
+                    UIEvent::Describe(text) => {
+                        if let ActionItem::Describe = active_action_item {
+                            describe_text = text;
+                        }
+                    }
+                    UIEvent::CrashReport(text) => {
+                        if let ActionItem::CrashReport = active_action_item {
+                            describe_text = text;
+                        }
+                    }
+                    UIEvent::LogLine(id, event) => {
+                        if id == log_pane.stream_id {
+                            log_pane.push(event);
+                        }
+                    }
+                    UIEvent::Resources(id, event) => {
+                        if id == resource_table.id {
+                            resource_table.update(event);
+                        }
+                    }
+                    UIEvent::ActionDone(result) => {
+                        let (text, error) = match result {
+                            Ok(t) => (t, false),
+                            Err(e) => (e, true),
+                        };
+                        toast = Some(Toast {
+                            text,
+                            error,
+                            shown: Instant::now(),
+                        });
+                    }
                 }
             }
 
            };
+
+        let restart = match command {
+            Some(Command::Pick(PickKind::Namespace)) => {
+                prompt = Some(Prompt::pick(PickKind::Namespace, vec![]));
+                load_namespaces(&ctx, ui_tx.clone());
+                false
+            }
+            Some(Command::Pick(PickKind::Context)) => {
+                match contexts(&opts.cluster) {
+                    Ok(names) => prompt = Some(Prompt::pick(PickKind::Context, names)),
+                    Err(e) => message = Some(format!("Failed to read contexts: {}", e)),
+                }
+                false
+            }
+            Some(Command::Namespace(n)) => {
+                namespace = n;
+                true
+            }
+            Some(Command::Context(name)) => {
+                match KubeContext::connect(&opts.cluster, Some(&name)).await {
+                    Ok(c) => {
+                        ctx = c;
+                        true
+                    }
+                    Err(e) => {
+                        message = Some(format!("Failed to switch to {}: {}", name, e));
+                        false
+                    }
+                }
+            }
+            None => false,
+        };
+        if restart {
+            pod_watch.abort();
+            log_pane.close();
+            match active_action_item {
+                ActionItem::Resources(kind) => {
+                    let n = namespace.as_deref();
+                    resource_table.open(&ctx, kind, n, ui_tx.clone());
+                }
+                _ => active_action_item = ActionItem::Home,
+            }
+            pod_table.set_pods(vec![]);
+            last_synced = None;
+            sync_error = None;
+            watch_id += 1;
+            let n = namespace.as_deref();
+            pod_watch = watch_pod_list(&ctx, n, lp.clone(), watch_id, ui_tx.clone());
+        }
     }
 
     Ok(())
@@ -247,90 +753,1191 @@ impl KubePod {
             rc += c.restart_count;
         }
 
-        let empty_str = String::new();
-        let phase = pod
-            .status
-            .as_ref()
-            .and_then(|s| s.phase.as_ref())
-            .unwrap_or(&empty_str);
+        let status = pod.status.as_ref();
 
         KubePod {
+            namespace: Meta::namespace(pod).unwrap_or_default(),
             name: Meta::name(pod),
+            containers: pod_containers(pod),
             ready: r,
             container_count: cs.len() as i32,
-            status: phase.to_string(),
+            status: pod_status(pod),
             restart_count: rc,
+            created: pod.metadata.creation_timestamp.as_ref().map(|t| t.0),
+            node: pod
+                .spec
+                .as_ref()
+                .and_then(|s| s.node_name.clone())
+                .unwrap_or_default(),
+            ip: status.and_then(|s| s.pod_ip.clone()).unwrap_or_default(),
+            owner: pod_controller(pod),
+            qos: status.and_then(|s| s.qos_class.clone()).unwrap_or_default(),
+            labels: pod
+                .metadata
+                .labels
+                .iter()
+                .flatten()
+                .map(|(k, v)| format!("{}={}", k, v))
+                .collect(),
+        }
+    }
+
+    fn cells(&self, show_namespace: bool) -> Vec<String> {
+        let mut cells = vec![];
+        if show_namespace {
+            cells.push(self.namespace.clone());
+        }
+        cells.extend(vec![
+            self.name.clone(),
+            format!("{}/{}", self.ready, self.container_count),
+            self.status.clone(),
+            self.restart_count.to_string(),
+            self.created
+                .as_ref()
+                .map(format_age)
+                .unwrap_or_else(|| String::from("<unknown>")),
+            self.ip.clone(),
+            self.node.clone(),
+            match &self.owner {
+                Some((kind, name)) => format!("{}/{}", kind, name),
+                None => String::from("<none>"),
+            },
+            self.qos.clone(),
+        ]);
+
+        cells
+    }
+}
+
+/// Keeps the pod table in sync with the pod watch, sending the whole table on every change.
+/// Events are tagged with `id` so those still queued from a replaced watch can be dropped.
+fn watch_pod_list(
+    ctx: &KubeContext,
+    namespace: Option<&str>,
+    lp: ListParams,
+    id: usize,
+    mut tx: tokio::sync::mpsc::Sender<UIEvent>,
+) -> AbortHandle {
+    let (pod_tx, mut pod_rx) = tokio::sync::mpsc::channel(100);
+    watch_pods(ctx, namespace, lp, pod_tx);
+    let (task, handle) = abortable(async move {
+        let mut cache: BTreeMap<String, Pod> = BTreeMap::new();
+        while let Some(event) = pod_rx.recv().await {
+            let ui_event = match event {
+                PodEvent::Restarted(pods) => {
+                    cache = pods.into_iter().map(|p| (qualified_name(&p), p)).collect();
+                    None
+                }
+                PodEvent::Applied(p) => {
+                    cache.insert(qualified_name(&p), p);
+                    None
+                }
+                PodEvent::Deleted(p) => {
+                    cache.remove(&qualified_name(&p));
+                    None
+                }
+                PodEvent::Synced => None,
+                PodEvent::Error(e) => Some(UIEvent::WatchError(id, e)),
+            };
+
+            let ui_event = ui_event.unwrap_or_else(|| {
+                UIEvent::RefreshPods(id, cache.values().map(|p| KubePod::new(p)).collect())
+            });
+            if tx.send(ui_event).await.is_err() {
+                break;
+            }
+        }
+    });
+    tokio::spawn(task);
+
+    handle
+}
+
+/// Forwards the rows of a resource watch, tagged with `id` like the pod table's.
+fn watch_resource_list(
+    ctx: &KubeContext,
+    kind: ResourceKind,
+    namespace: Option<&str>,
+    id: usize,
+    mut tx: tokio::sync::mpsc::Sender<UIEvent>,
+) -> AbortHandle {
+    let (row_tx, mut row_rx) = tokio::sync::mpsc::channel(100);
+    watch_rows(ctx, kind, namespace, row_tx);
+    let (task, handle) = abortable(async move {
+        while let Some(event) = row_rx.recv().await {
+            if tx.send(UIEvent::Resources(id, event)).await.is_err() {
+                break;
+            }
         }
+    });
+    tokio::spawn(task);
+
+    handle
+}
+
+/// The tab selected by `c`: its number in the tab bar or its underlined letter.
+fn tab_for_key(c: char) -> Option<ActionItem> {
+    match c {
+        '1' | 'P' => Some(ActionItem::Home),
+        '2'..='9' => RESOURCE_KINDS
+            .get(c as usize - '2' as usize)
+            .map(|k| ActionItem::Resources(*k)),
+        _ => RESOURCE_KINDS
+            .iter()
+            .find(|k| k.key() == c)
+            .map(|k| ActionItem::Resources(*k)),
     }
 }
 
-fn refresh_pod_list(namespace: &str, mut tx: tokio::sync::mpsc::Sender<UIEvent>) -> Result<()> {
-    let n: String = namespace.into();
-    tokio::spawn(async move {
-        match get_pods(&n).await {
-            Ok(l) => {
-                let pod_list: Vec<KubePod> = l.iter().map(|p| KubePod::new(p)).collect();
+/// Whether `c` switches tabs. Tab keys are matched before any `[keys]` rebinding in the table
+/// views, so actions of those views cannot be rebound to them.
+pub fn is_tab_key(c: char) -> bool {
+    tab_for_key(c).is_some()
+}
+
+/// Whether the active view is one of the tab tables, where the tab keys apply.
+fn is_table(item: ActionItem) -> bool {
+    matches!(item, ActionItem::Home | ActionItem::Resources(_))
+}
 
-                let _ = tx.send(UIEvent::RefreshPods(pod_list)).await;
+fn render_status<'a>(
+    context: &'a str,
+    namespace: &'a str,
+    last_synced: Option<&DateTime<Utc>>,
+    sync_error: Option<&str>,
+    message: Option<&str>,
+    toast: Option<&Toast>,
+) -> Spans<'a> {
+    let sync = match (sync_error, last_synced) {
+        (Some(e), _) => Span::styled(
+            format!("reconnecting: {}", e),
+            Style::default().fg(Color::Red),
+        ),
+        (None, Some(t)) => Span::styled(
+            format!("last synced {} ago", format_age(t)),
+            Style::default().fg(Color::Green),
+        ),
+        (None, None) => Span::styled("syncing...", Style::default().fg(Color::Yellow)),
+    };
+
+    let mut spans = vec![
+        Span::styled(context, Style::default().fg(Color::LightCyan)),
+        Span::raw(" | "),
+        Span::styled(namespace, Style::default().fg(Color::LightCyan)),
+        Span::raw(" | "),
+        sync,
+    ];
+    if let Some(m) = message {
+        spans.push(Span::raw(" | "));
+        spans.push(Span::styled(m.to_string(), Style::default().fg(Color::Red)));
+    }
+    if let Some(t) = toast {
+        let color = if t.error { Color::Red } else { Color::Green };
+        spans.push(Span::raw(" | "));
+        spans.push(Span::styled(t.text.clone(), Style::default().fg(color)));
+    }
+
+    Spans::from(spans)
+}
+
+impl Confirm {
+    fn new(action: Action, pod: &KubePod) -> Self {
+        Confirm {
+            action,
+            namespace: pod.namespace.clone(),
+            name: pod.name.clone(),
+            owner: pod.owner.clone(),
+            input: String::new(),
+            armed: false,
+        }
+    }
+
+    fn scale(kind: &'static str, row: &ResourceRow) -> Self {
+        Confirm {
+            action: Action::Scale(kind),
+            namespace: row.namespace.clone(),
+            name: row.name.clone(),
+            owner: None,
+            input: String::new(),
+            armed: false,
+        }
+    }
+
+    fn takes_input(&self) -> bool {
+        matches!(self.action, Action::Delete | Action::Scale(_))
+    }
+
+    fn render(&self) -> Paragraph {
+        let pod = format!("{}/{}", self.namespace, self.name);
+        let mut lines = match self.action {
+            Action::Delete => vec![
+                Spans::from(format!("Delete pod {}?", pod)),
+                Spans::from(""),
+                Spans::from(match self.input.as_str() {
+                    "" => String::from("Grace period: the pod's own (type seconds to change)"),
+                    s => format!("Grace period: {}s", s),
+                }),
+            ],
+            Action::ForceDelete => vec![
+                Spans::from(format!("Force delete pod {}?", pod)),
+                Spans::from(""),
+                Spans::from("It is removed right away, without waiting for its containers."),
+            ],
+            Action::Restart => vec![
+                Spans::from(match &self.owner {
+                    Some((kind, name)) => format!("Restart {}/{}, which owns {}?", kind, name, pod),
+                    None => format!("{} has no owner to restart.", pod),
+                }),
+                Spans::from(""),
+                Spans::from(
+                    "Every pod of the workload is replaced, as by kubectl rollout restart.",
+                ),
+            ],
+            Action::Scale(kind) => vec![
+                Spans::from(format!("Scale {} {}/{}", kind, self.namespace, self.name)),
+                Spans::from(""),
+                Spans::from(format!("Replicas: {}_", self.input)),
+                Spans::from(if self.armed {
+                    Span::styled(
+                        "Scaling to zero stops every pod. Press y again to go ahead.",
+                        Style::default().fg(Color::Red),
+                    )
+                } else {
+                    Span::raw("")
+                }),
+            ],
+        };
+        lines.push(Spans::from(""));
+        lines.push(Spans::from(Span::styled(
+            "y to confirm, n or Esc to cancel",
+            Style::default().fg(Color::Yellow),
+        )));
+
+        Paragraph::new(lines).wrap(Wrap { trim: true }).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .style(Style::default().fg(Color::White))
+                .title("Confirm")
+                .border_type(BorderType::Plain),
+        )
+    }
+}
+
+/// Handles a key while the confirmation dialog is open, returning it once confirmed.
+fn handle_confirm(confirm: &mut Option<Confirm>, code: KeyCode) -> Option<Confirm> {
+    match (confirm.as_mut(), code) {
+        (_, KeyCode::Esc) | (_, KeyCode::Char('n')) => *confirm = None,
+        (Some(c), KeyCode::Char('y')) | (Some(c), KeyCode::Enter) => {
+            if let Action::Scale(_) = c.action {
+                match c.input.parse::<i32>() {
+                    Ok(0) if !c.armed => {
+                        c.armed = true;
+                        return None;
+                    }
+                    Ok(_) => {}
+                    Err(_) => return None,
+                }
             }
-            Err(e) => println!("{:?}", e),
+            return confirm.take();
         }
+        (Some(c), KeyCode::Char(d)) if c.takes_input() && d.is_ascii_digit() => {
+            c.input.push(d);
+            c.armed = false;
+        }
+        (Some(c), KeyCode::Backspace) => {
+            c.input.pop();
+            c.armed = false;
+        }
+        _ => {}
+    }
+
+    None
+}
+
+fn run_action(ctx: &KubeContext, c: Confirm, mut tx: tokio::sync::mpsc::Sender<UIEvent>) {
+    let ctx = ctx.clone();
+    tokio::spawn(async move {
+        let (ns, pod) = (&c.namespace, &c.name);
+        let result = match c.action {
+            Action::Delete => delete_pod(&ctx, ns, pod, c.input.parse().ok())
+                .await
+                .map(|_| format!("Deleted pod {}", pod)),
+            Action::ForceDelete => delete_pod(&ctx, ns, pod, Some(0))
+                .await
+                .map(|_| format!("Force deleted pod {}", pod)),
+            Action::Restart => match &c.owner {
+                Some((kind, name)) => restart_owner(&ctx, ns, kind, name)
+                    .await
+                    .map(|w| format!("Restarted {}", w)),
+                None => Err(anyhow!("pod {} has no owner to restart", pod)),
+            },
+            Action::Scale(kind) => {
+                let replicas = c.input.parse().unwrap_or_default();
+                scale_workload(&ctx, ns, kind, &c.name, replicas)
+                    .await
+                    .map(|_| format!("Scaled {}/{} to {} replicas", kind, c.name, replicas))
+            }
+        };
+
+        let result = result.map_err(|e| format!("Failed: {}", e));
+        let _ = tx.send(UIEvent::ActionDone(result)).await;
     });
+}
 
-    Ok(())
+fn load_namespaces(ctx: &KubeContext, mut tx: tokio::sync::mpsc::Sender<UIEvent>) {
+    let ctx = ctx.clone();
+    tokio::spawn(async move {
+        let event = match ctx.namespaces().await {
+            Ok(mut names) => {
+                names.insert(0, ALL_NAMESPACES.to_string());
+                UIEvent::Choices(PickKind::Namespace, names)
+            }
+            Err(e) => UIEvent::Message(format!("Failed to list namespaces: {}", e)),
+        };
+
+        let _ = tx.send(event).await;
+    });
 }
 
-fn render_pods<'a>(pod_list: &[KubePod]) -> Table<'a> {
-    let rows: Vec<_> = pod_list
-        .iter()
-        .map(|p| {
-            Row::new(vec![
-                Cell::from(Span::raw(p.name.to_string())),
-                Cell::from(Span::raw(format!("{}/{}", p.ready, p.container_count))),
-                Cell::from(Span::raw(format!("{}", p.restart_count))),
-                Cell::from(Span::raw(p.status.clone())),
-            ])
-        })
-        .collect();
+impl Prompt {
+    fn pick(kind: PickKind, items: Vec<String>) -> Self {
+        let mut state = ListState::default();
+        state.select(Some(0));
+        Prompt::Pick { kind, items, state }
+    }
+}
 
-    let pod_detail = Table::new(rows)
-        .header(Row::new(vec![
-            Cell::from(Span::styled(
-                "Name",
-                Style::default().add_modifier(Modifier::BOLD),
-            )),
-            Cell::from(Span::styled(
-                "Ready",
-                Style::default().add_modifier(Modifier::BOLD),
-            )),
-            Cell::from(Span::styled(
-                "Restarts",
-                Style::default().add_modifier(Modifier::BOLD),
-            )),
-            Cell::from(Span::styled(
-                "Status",
-                Style::default().add_modifier(Modifier::BOLD),
-            )),
-        ]))
+/// Handles a key while the prompt is open, returning the command once one is entered or picked.
+fn handle_prompt(prompt: &mut Option<Prompt>, code: KeyCode) -> Result<Option<Command>, String> {
+    match (prompt.as_mut(), code) {
+        (_, KeyCode::Esc) => *prompt = None,
+        (Some(Prompt::Input(input)), KeyCode::Char(c)) => input.push(c),
+        (Some(Prompt::Input(input)), KeyCode::Backspace) => {
+            input.pop();
+        }
+        (Some(Prompt::Input(input)), KeyCode::Enter) => {
+            let command = parse_command(input);
+            *prompt = None;
+            return command;
+        }
+        (Some(Prompt::Pick { items, state, .. }), code)
+            if code == KeyCode::Down || code == KeyCode::Char('j') =>
+        {
+            let next = state.selected().map_or(0, |i| i + 1);
+            state.select(Some(next.min(items.len().saturating_sub(1))));
+        }
+        (Some(Prompt::Pick { state, .. }), code)
+            if code == KeyCode::Up || code == KeyCode::Char('k') =>
+        {
+            let prev = state.selected().map_or(0, |i| i.saturating_sub(1));
+            state.select(Some(prev));
+        }
+        (Some(Prompt::Pick { kind, items, state }), KeyCode::Enter) => {
+            let command = state
+                .selected()
+                .and_then(|i| items.get(i))
+                .map(|item| match kind {
+                    PickKind::Namespace if item == ALL_NAMESPACES => Command::Namespace(None),
+                    PickKind::Namespace => Command::Namespace(Some(item.clone())),
+                    PickKind::Context => Command::Context(item.clone()),
+                });
+            *prompt = None;
+            return Ok(command);
+        }
+        _ => {}
+    }
+
+    Ok(None)
+}
+
+/// Parses `ns [name|-A]` or `ctx [name]`; without a name a list to pick from is opened.
+fn parse_command(input: &str) -> Result<Option<Command>, String> {
+    let mut words = input.split_whitespace();
+    let kind = match words.next() {
+        Some("ns") | Some("namespace") => PickKind::Namespace,
+        Some("ctx") | Some("context") => PickKind::Context,
+        Some(other) => return Err(format!("unknown command '{}', expected ns or ctx", other)),
+        None => return Ok(None),
+    };
+
+    let command = match (kind, words.next()) {
+        (_, None) => Command::Pick(kind),
+        (PickKind::Namespace, Some("-A")) => Command::Namespace(None),
+        (PickKind::Namespace, Some(n)) => Command::Namespace(Some(n.to_string())),
+        (PickKind::Context, Some(c)) => Command::Context(c.to_string()),
+    };
+
+    Ok(Some(command))
+}
+
+fn render_picker(kind: PickKind, items: &[String]) -> List {
+    let title = match kind {
+        PickKind::Namespace => "Namespaces",
+        PickKind::Context => "Contexts",
+    };
+    let items: Vec<ListItem> = if items.is_empty() {
+        vec![ListItem::new("Loading...")]
+    } else {
+        items.iter().map(|i| ListItem::new(i.as_str())).collect()
+    };
+
+    List::new(items)
         .block(
             Block::default()
                 .borders(Borders::ALL)
                 .style(Style::default().fg(Color::White))
-                .title("Detail")
+                .title(title)
                 .border_type(BorderType::Plain),
         )
-        .widths(&[
-            Constraint::Percentage(20),
-            Constraint::Percentage(5),
-            Constraint::Percentage(5),
-            Constraint::Percentage(70),
-        ])
         .highlight_style(
             Style::default()
                 .bg(Color::Green)
                 .fg(Color::Black)
                 .add_modifier(Modifier::BOLD),
+        )
+}
+
+/// A box in the middle of `area` for the picker.
+fn popup_area(area: Rect) -> Rect {
+    let width = area.width / 2;
+    let height = (area.height * 3 / 5).max(3).min(area.height);
+
+    Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + (area.height - height) / 2,
+        width,
+        height,
+    )
+}
+
+fn load_describe(
+    ctx: &KubeContext,
+    namespace: &str,
+    pod_name: &str,
+    mut tx: tokio::sync::mpsc::Sender<UIEvent>,
+) {
+    let ctx = ctx.clone();
+    let n: String = namespace.into();
+    let pn: String = pod_name.into();
+    tokio::spawn(async move {
+        let text = match describe_pod(&ctx, &n, &pn).await {
+            Ok(t) => t,
+            Err(e) => format!("Failed to describe {}: {:?}", pn, e),
+        };
+
+        let _ = tx.send(UIEvent::Describe(text)).await;
+    });
+}
+
+fn load_crash_report(
+    ctx: &KubeContext,
+    namespace: &str,
+    pod_name: &str,
+    tail_lines: i64,
+    mut tx: tokio::sync::mpsc::Sender<UIEvent>,
+) {
+    let ctx = ctx.clone();
+    let n: String = namespace.into();
+    let pn: String = pod_name.into();
+    tokio::spawn(async move {
+        let text = match crash_report(&ctx, &n, &pn, tail_lines).await {
+            Ok(t) => t,
+            Err(e) => format!("Failed to load crash report for {}: {:?}", pn, e),
+        };
+
+        let _ = tx.send(UIEvent::CrashReport(text)).await;
+    });
+}
+
+impl ResourceTable {
+    fn new() -> Self {
+        ResourceTable {
+            kind: RESOURCE_KINDS[0],
+            rows: vec![],
+            state: TableState::default(),
+            error: None,
+            id: 0,
+            watch: None,
+        }
+    }
+
+    fn open(
+        &mut self,
+        ctx: &KubeContext,
+        kind: ResourceKind,
+        namespace: Option<&str>,
+        tx: tokio::sync::mpsc::Sender<UIEvent>,
+    ) {
+        self.close();
+        self.kind = kind;
+        self.rows.clear();
+        self.state.select(Some(0));
+        self.error = None;
+        self.id += 1;
+        self.watch = Some(watch_resource_list(ctx, kind, namespace, self.id, tx));
+    }
+
+    fn close(&mut self) {
+        if let Some(handle) = self.watch.take() {
+            handle.abort();
+        }
+    }
+
+    fn update(&mut self, event: RowEvent) {
+        match event {
+            RowEvent::Rows(rows) => {
+                self.rows = rows;
+                self.error = None;
+                if let Some(selected) = self.state.selected() {
+                    if selected >= self.rows.len() {
+                        self.state.select(Some(self.rows.len().saturating_sub(1)));
+                    }
+                }
+            }
+            RowEvent::Error(e) => self.error = Some(e),
+        }
+    }
+
+    fn selected(&self) -> Option<&ResourceRow> {
+        self.state.selected().and_then(|i| self.rows.get(i))
+    }
+
+    fn next(&mut self) {
+        let next = match self.state.selected() {
+            Some(i) if i + 1 < self.rows.len() => i + 1,
+            _ => 0,
+        };
+        self.state.select(Some(next));
+    }
+
+    fn previous(&mut self) {
+        let prev = match self.state.selected() {
+            Some(i) if i > 0 => i - 1,
+            _ => self.rows.len().saturating_sub(1),
+        };
+        self.state.select(Some(prev));
+    }
+
+    fn show_namespace(&self, all_namespaces: bool) -> bool {
+        all_namespaces && self.kind.namespaced()
+    }
+
+    fn headers(&self, all_namespaces: bool) -> Vec<&'static str> {
+        let mut titles = self.kind.headers().to_vec();
+        if self.show_namespace(all_namespaces) {
+            titles.insert(0, "Namespace");
+        }
+
+        titles
+    }
+
+    fn cells(&self, all_namespaces: bool) -> Vec<Vec<String>> {
+        let show_namespace = self.show_namespace(all_namespaces);
+        self.rows
+            .iter()
+            .map(|r| {
+                let mut cells = vec![];
+                if show_namespace {
+                    cells.push(r.namespace.clone());
+                }
+                cells.extend(r.cells.iter().cloned());
+                cells
+            })
+            .collect()
+    }
+
+    fn widths(&self, all_namespaces: bool) -> Vec<Constraint> {
+        column_widths(&self.headers(all_namespaces), &self.cells(all_namespaces))
+    }
+
+    fn render<'a>(&self, all_namespaces: bool, widths: &'a [Constraint]) -> Table<'a> {
+        let rows: Vec<_> = self
+            .cells(all_namespaces)
+            .into_iter()
+            .map(|cells| Row::new(cells.into_iter().map(|c| Cell::from(Span::raw(c)))))
+            .collect();
+
+        let header = self.headers(all_namespaces).into_iter().map(|t| {
+            Cell::from(Span::styled(
+                t,
+                Style::default().add_modifier(Modifier::BOLD),
+            ))
+        });
+
+        let title = match &self.error {
+            Some(e) => format!("{} (reconnecting: {})", self.kind.title(), e),
+            None => self.kind.title().to_string(),
+        };
+
+        Table::new(rows)
+            .header(Row::new(header))
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .style(Style::default().fg(Color::White))
+                    .title(title)
+                    .border_type(BorderType::Plain),
+            )
+            .widths(widths)
+            .highlight_style(
+                Style::default()
+                    .bg(Color::Green)
+                    .fg(Color::Black)
+                    .add_modifier(Modifier::BOLD),
+            )
+    }
+}
+
+impl LogPane {
+    fn new(formatter: LineFormatter, json: JsonFormat, tail_length: i64) -> Self {
+        LogPane {
+            namespace: String::new(),
+            pod: String::new(),
+            containers: vec![],
+            container: 0,
+            stream_id: 0,
+            stream_name: String::new(),
+            lines: VecDeque::new(),
+            pending: VecDeque::new(),
+            pending_count: 0,
+            formatter,
+            json,
+            query: None,
+            input: None,
+            tail_length,
+            offset: 0,
+            paused: false,
+            wrap: false,
+            previous: false,
+            stream: None,
+        }
+    }
+
+    fn open(
+        &mut self,
+        ctx: &KubeContext,
+        namespace: &str,
+        pod: &KubePod,
+        tx: tokio::sync::mpsc::Sender<UIEvent>,
+    ) {
+        self.namespace = namespace.to_string();
+        self.pod = pod.name.clone();
+        self.containers = pod.containers.clone();
+        self.container = 0;
+        self.previous = false;
+        self.start(ctx, tx);
+    }
+
+    /// Switches between the running container and the logs of its previous instance.
+    fn toggle_previous(&mut self, ctx: &KubeContext, tx: tokio::sync::mpsc::Sender<UIEvent>) {
+        self.previous = !self.previous;
+        self.start(ctx, tx);
+    }
+
+    fn next_container(&mut self, ctx: &KubeContext, tx: tokio::sync::mpsc::Sender<UIEvent>) {
+        if self.containers.len() > 1 {
+            self.container = (self.container + 1) % self.containers.len();
+            self.start(ctx, tx);
+        }
+    }
+
+    fn start(&mut self, ctx: &KubeContext, mut tx: tokio::sync::mpsc::Sender<UIEvent>) {
+        self.close();
+        let container = self.containers.get(self.container).cloned();
+        self.stream_name = match &container {
+            Some(c) => format!("{}/{}", self.pod, c),
+            None => self.pod.clone(),
+        };
+        if self.previous {
+            self.stream_name.push_str(" (previous)");
+        }
+        self.lines.clear();
+        self.pending.clear();
+        self.pending_count = 0;
+        self.offset = 0;
+        self.paused = false;
+
+        let mut lp = follow_params(self.tail_length, container);
+        if self.previous {
+            lp.previous = true;
+            lp.follow = false;
+        }
+
+        let (line_tx, mut line_rx) = tokio::sync::mpsc::channel(100);
+        let reader = read_log_lines(
+            ctx.clone(),
+            self.namespace.clone(),
+            self.pod.clone(),
+            lp,
+            line_tx,
+        );
+        let id = self.stream_id;
+        let (stream, handle) = abortable(async move {
+            let forward = async {
+                while let Some(line) = line_rx.recv().await {
+                    if tx.send(UIEvent::LogLine(id, line)).await.is_err() {
+                        break;
+                    }
+                }
+            };
+            let (res, _) = futures::join!(reader, forward);
+            let end = match res {
+                Ok(_) => LogEvent::Notice(String::from("log stream closed")),
+                Err(e) => LogEvent::Notice(format!("log stream failed: {}", e)),
+            };
+            let _ = tx.send(UIEvent::LogLine(id, end)).await;
+        });
+        tokio::spawn(stream);
+        self.stream = Some(handle);
+    }
+
+    fn close(&mut self) {
+        if let Some(handle) = self.stream.take() {
+            handle.abort();
+        }
+        self.stream_id += 1;
+    }
+
+    fn push(&mut self, event: LogEvent) {
+        let line = match event {
+            LogEvent::Line { text, .. } => {
+                let (kind, rendered) = self.formatter.format(&text);
+                PaneLine {
+                    kind,
+                    text: rendered,
+                    raw: text,
+                    visible: true,
+                }
+            }
+            LogEvent::Notice(n) => PaneLine {
+                kind: LineKind::Notice,
+                text: format!("--- {} ---", n),
+                raw: String::new(),
+                visible: true,
+            },
+        };
+        if self.paused {
+            self.pending.push_back(line);
+            self.pending_count += 1;
+            if self.pending.len() > LOG_SCROLLBACK {
+                self.pending.pop_front();
+            }
+            return;
+        }
+
+        self.append(line);
+    }
+
+    fn append(&mut self, mut line: PaneLine) {
+        line.visible = self.is_visible(&line);
+        // Keep the view anchored while scrolled back through history.
+        if self.offset > 0 && line.visible {
+            self.offset += 1;
+        }
+        self.lines.push_back(line);
+        if self.lines.len() > LOG_SCROLLBACK {
+            self.lines.pop_front();
+        }
+        self.offset = self.offset.min(self.visible_count());
+    }
+
+    fn is_visible(&self, line: &PaneLine) -> bool {
+        line.kind == LineKind::Notice || self.query.as_ref().map_or(true, |q| q.matches(&line.raw))
+    }
+
+    fn visible_count(&self) -> usize {
+        self.lines.iter().filter(|l| l.visible).count()
+    }
+
+    fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+        if !self.paused {
+            self.pending_count = 0;
+            let pending: Vec<_> = self.pending.drain(..).collect();
+            for line in pending {
+                self.append(line);
+            }
+        }
+    }
+
+    fn editing(&self) -> bool {
+        self.input.is_some()
+    }
+
+    fn start_editing(&mut self) {
+        let current = self.query.as_ref().map(|q| q.source().to_string());
+        self.input = Some(current.unwrap_or_default());
+    }
+
+    /// Handles a key while the filter prompt is open.
+    fn edit(&mut self, code: KeyCode) {
+        let input = match self.input.as_mut() {
+            Some(i) => i,
+            None => return,
+        };
+
+        match code {
+            KeyCode::Char(c) => input.push(c),
+            KeyCode::Backspace => {
+                input.pop();
+            }
+            KeyCode::Esc => self.input = None,
+            KeyCode::Enter => {
+                let source = self.input.take().unwrap_or_default();
+                if source.trim().is_empty() {
+                    self.set_query(None);
+                } else {
+                    match Query::parse(&source, self.json.clone()) {
+                        Ok(q) => self.set_query(Some(q)),
+                        Err(e) => self.push(LogEvent::Notice(format!("invalid filter: {}", e))),
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn set_query(&mut self, query: Option<Query>) {
+        self.query = query;
+        let visible: Vec<bool> = self.lines.iter().map(|l| self.is_visible(l)).collect();
+        for (line, visible) in self.lines.iter_mut().zip(visible) {
+            line.visible = visible;
+        }
+        self.offset = 0;
+    }
+
+    fn scroll_up(&mut self, n: usize) {
+        self.offset = (self.offset + n).min(self.visible_count().saturating_sub(1));
+    }
+
+    fn scroll_down(&mut self, n: usize) {
+        self.offset = self.offset.saturating_sub(n);
+    }
+
+    fn render(&self, area: Rect) -> Paragraph {
+        let height = area.height.saturating_sub(2) as usize;
+        let width = area.width.saturating_sub(2).max(1) as usize;
+        let lines: Vec<&PaneLine> = self.lines.iter().filter(|l| l.visible).collect();
+        let end = lines.len().saturating_sub(self.offset);
+        let mut start = end;
+        let mut rows = 0;
+        while start > 0 && rows < height {
+            let len = lines[start - 1].text.chars().count();
+            rows += if self.wrap {
+                ((len + width - 1) / width).max(1)
+            } else {
+                1
+            };
+            start -= 1;
+        }
+
+        let text: Vec<Spans> = lines[start..end]
+            .iter()
+            .map(|l| Spans::from(Span::styled(l.text.as_str(), line_style(l.kind))))
+            .collect();
+
+        let mut title = format!("Logs: {}", self.stream_name);
+        if self.containers.len() > 1 {
+            title.push_str(&format!(
+                " [container {}/{}]",
+                self.container + 1,
+                self.containers.len()
+            ));
+        }
+        if self.paused {
+            title.push_str(&format!(" [paused, {} new]", self.pending_count));
+        }
+        if self.offset > 0 {
+            title.push_str(&format!(" [-{}]", self.offset));
+        }
+        if self.wrap {
+            title.push_str(" [wrap]");
+        }
+        match (&self.input, &self.query) {
+            (Some(input), _) => title.push_str(&format!(" filter: {}_", input)),
+            (None, Some(q)) => title.push_str(&format!(" [filter: {}]", q.source())),
+            (None, None) => {}
+        }
+
+        let logs = Paragraph::new(text).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .style(Style::default().fg(Color::White))
+                .title(title)
+                .border_type(BorderType::Plain),
         );
 
-    pod_detail
+        if self.wrap {
+            // Wrapped lines can overflow the pane, so skip the rows hidden above the top.
+            logs.wrap(Wrap { trim: false })
+                .scroll((rows.saturating_sub(height) as u16, 0))
+        } else {
+            logs
+        }
+    }
+}
+
+fn line_style(kind: LineKind) -> Style {
+    match kind {
+        LineKind::Error => Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+        LineKind::Highlight => Style::default()
+            .fg(Color::Yellow)
+            .add_modifier(Modifier::BOLD),
+        LineKind::Warn => Style::default().fg(Color::Yellow),
+        LineKind::Debug => Style::default().fg(Color::DarkGray),
+        LineKind::Notice => Style::default()
+            .fg(Color::Cyan)
+            .add_modifier(Modifier::ITALIC),
+        LineKind::Plain => Style::default().fg(Color::White),
+    }
+}
+
+fn max_scroll(text: &str, scroll: u16) -> u16 {
+    let lines = text.lines().count().saturating_sub(1) as u16;
+    scroll.min(lines)
+}
+
+impl PodSort {
+    fn title(self) -> &'static str {
+        match self {
+            PodSort::Name => "Name",
+            PodSort::Age => "Age",
+            PodSort::Restarts => "Restarts",
+            PodSort::Status => "Status",
+        }
+    }
+}
+
+impl PodTable {
+    fn new() -> Self {
+        let mut state = TableState::default();
+        state.select(Some(0));
+
+        PodTable {
+            pods: vec![],
+            visible: vec![],
+            state,
+            filter: String::new(),
+            editing: false,
+            sort: None,
+            descending: false,
+        }
+    }
+
+    fn set_pods(&mut self, pods: Vec<KubePod>) {
+        self.pods = pods;
+        self.refresh();
+    }
+
+    fn set_filter(&mut self, filter: String) {
+        self.filter = filter;
+        self.refresh();
+    }
+
+    /// Sorts by `sort`, or flips the direction when already sorted by it.
+    fn sort_by(&mut self, sort: PodSort) {
+        if self.sort == Some(sort) {
+            self.descending = !self.descending;
+        } else {
+            self.sort = Some(sort);
+            self.descending = false;
+        }
+        self.refresh();
+    }
+
+    /// Handles a key while the filter prompt is open, filtering as the pattern is typed.
+    fn edit(&mut self, code: KeyCode) {
+        match code {
+            KeyCode::Char(c) => {
+                let mut filter = self.filter.clone();
+                filter.push(c);
+                self.set_filter(filter);
+            }
+            KeyCode::Backspace => {
+                let mut filter = self.filter.clone();
+                filter.pop();
+                self.set_filter(filter);
+            }
+            KeyCode::Esc => {
+                self.editing = false;
+                self.set_filter(String::new());
+            }
+            KeyCode::Enter => self.editing = false,
+            _ => {}
+        }
+    }
+
+    /// Rebuilds `visible`, keeping the selection on the same pod when it is still shown.
+    fn refresh(&mut self) {
+        let selected = self
+            .selected()
+            .map(|p| (p.namespace.clone(), p.name.clone()));
+
+        let filter = self.filter.to_lowercase();
+        let mut visible: Vec<usize> = (0..self.pods.len())
+            .filter(|i| {
+                let p = &self.pods[*i];
+                fuzzy_match(&filter, &p.name) || p.labels.iter().any(|l| fuzzy_match(&filter, l))
+            })
+            .collect();
+        if let Some(sort) = self.sort {
+            let pods = &self.pods;
+            visible.sort_by(|a, b| {
+                let (a, b) = (&pods[*a], &pods[*b]);
+                match sort {
+                    PodSort::Name => a.name.cmp(&b.name),
+                    // Newest first, as that is what the smallest age means.
+                    PodSort::Age => b.created.cmp(&a.created),
+                    PodSort::Restarts => a.restart_count.cmp(&b.restart_count),
+                    PodSort::Status => a.status.cmp(&b.status),
+                }
+                .then_with(|| a.name.cmp(&b.name))
+            });
+            if self.descending {
+                visible.reverse();
+            }
+        }
+        self.visible = visible;
+
+        let index = selected.and_then(|(namespace, name)| {
+            self.visible.iter().position(|i| {
+                let p = &self.pods[*i];
+                p.namespace == namespace && p.name == name
+            })
+        });
+        let last = self.visible.len().saturating_sub(1);
+        self.state.select(Some(
+            index.unwrap_or_else(|| self.state.selected().unwrap_or(0).min(last)),
+        ));
+    }
+
+    fn selected(&self) -> Option<&KubePod> {
+        self.state
+            .selected()
+            .and_then(|i| self.visible.get(i))
+            .map(|i| &self.pods[*i])
+    }
+
+    fn next(&mut self) {
+        let next = match self.state.selected() {
+            Some(i) if i + 1 < self.visible.len() => i + 1,
+            _ => 0,
+        };
+        self.state.select(Some(next));
+    }
+
+    fn previous(&mut self) {
+        let prev = match self.state.selected() {
+            Some(i) if i > 0 => i - 1,
+            _ => self.visible.len().saturating_sub(1),
+        };
+        self.state.select(Some(prev));
+    }
+
+    /// The column titles, with an arrow on the one being sorted by.
+    fn headers(&self, show_namespace: bool) -> Vec<String> {
+        let mut titles = vec![
+            "Name", "Ready", "Status", "Restarts", "Age", "IP", "Node", "Owner", "QoS",
+        ];
+        if show_namespace {
+            titles.insert(0, "Namespace");
+        }
+
+        titles
+            .into_iter()
+            .map(|t| match self.sort {
+                Some(sort) if sort.title() == t => {
+                    format!("{} {}", t, if self.descending { "▼" } else { "▲" })
+                }
+                _ => t.to_string(),
+            })
+            .collect()
+    }
+
+    fn cells(&self, show_namespace: bool) -> Vec<Vec<String>> {
+        self.visible
+            .iter()
+            .map(|i| self.pods[*i].cells(show_namespace))
+            .collect()
+    }
+
+    fn widths(&self, show_namespace: bool) -> Vec<Constraint> {
+        column_widths(&self.headers(show_namespace), &self.cells(show_namespace))
+    }
+
+    fn render<'a>(&self, show_namespace: bool, widths: &'a [Constraint]) -> Table<'a> {
+        let rows: Vec<_> = self
+            .cells(show_namespace)
+            .into_iter()
+            .map(|cells| Row::new(cells.into_iter().map(|c| Cell::from(Span::raw(c)))))
+            .collect();
+
+        let header = self.headers(show_namespace).into_iter().map(|t| {
+            Cell::from(Span::styled(
+                t,
+                Style::default().add_modifier(Modifier::BOLD),
+            ))
+        });
+
+        let mut title = format!("Detail [{}/{}]", self.visible.len(), self.pods.len());
+        if self.editing {
+            title.push_str(&format!(" filter: {}_", self.filter));
+        } else if !self.filter.is_empty() {
+            title.push_str(&format!(" [filter: {}]", self.filter));
+        }
+
+        let pod_detail = Table::new(rows)
+            .header(Row::new(header))
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .style(Style::default().fg(Color::White))
+                    .title(title)
+                    .border_type(BorderType::Plain),
+            )
+            .widths(widths)
+            .highlight_style(
+                Style::default()
+                    .bg(Color::Green)
+                    .fg(Color::Black)
+                    .add_modifier(Modifier::BOLD),
+            );
+
+        pod_detail
+    }
+}
+
+/// Whether the characters of `pattern` appear in `text` in order, ignoring case. `pattern`
+/// is expected to be lowercase already.
+fn fuzzy_match(pattern: &str, text: &str) -> bool {
+    let mut text = text.chars().flat_map(char::to_lowercase);
+    pattern.chars().all(|p| text.any(|t| t == p))
+}
+
+/// Sizes each column to its widest cell, capped at `MAX_COLUMN_WIDTH`, and lets the last
+/// column take whatever room is left.
+fn column_widths<S: AsRef<str>>(headers: &[S], rows: &[Vec<String>]) -> Vec<Constraint> {
+    let mut widths: Vec<u16> = headers
+        .iter()
+        .map(|h| h.as_ref().chars().count() as u16)
+        .collect();
+    for row in rows {
+        for (w, cell) in widths.iter_mut().zip(row) {
+            *w = (*w).max(cell.chars().count() as u16);
+        }
+    }
+
+    let last = widths.len().saturating_sub(1);
+    widths
+        .into_iter()
+        .enumerate()
+        .map(|(i, w)| {
+            let w = w.min(MAX_COLUMN_WIDTH);
+            if i == last {
+                Constraint::Min(w)
+            } else {
+                Constraint::Length(w)
+            }
+        })
+        .collect()
 }
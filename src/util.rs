@@ -1,6 +1,17 @@
-use anyhow::Result;
-use k8s_openapi::api::core::v1::Pod;
-use kube::{api::ListParams, config::Kubeconfig, Api, Client, Config};
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Duration, Utc};
+use k8s_openapi::api::{
+    apps::v1::{DaemonSet, Deployment, ReplicaSet, StatefulSet},
+    core::v1::{Event, Pod},
+};
+use kube::{
+    api::{DeleteParams, ListParams, LogParams, Meta, PatchParams, PatchStrategy},
+    Api,
+};
+use serde::de::DeserializeOwned;
+use serde_json::json;
+
+use crate::{context::KubeContext, describe};
 
 pub trait OptionEx {
     fn to_str(&self) -> String;
@@ -16,36 +27,360 @@ impl OptionEx for Option<String> {
     }
 }
 
-pub async fn get_pods(namespace: &str) -> Result<Vec<Pod>> {
-    let mut client_config = Config::infer().await?;
-    // client_config.timeout = std::time::Duration::from_secs(60 * 60 * 24);
-    client_config.timeout = None;
-    let client = Client::new(client_config);
+/// `namespace/name`, which identifies an object across namespaces.
+pub fn qualified_name<K: Meta>(obj: &K) -> String {
+    format!(
+        "{}/{}",
+        Meta::namespace(obj).unwrap_or_default(),
+        Meta::name(obj)
+    )
+}
 
-    let pods: Api<Pod> = Api::namespaced(client, namespace);
+pub fn pod_list_params(selector: &Option<String>, field_selector: &Option<String>) -> ListParams {
     let mut lp = ListParams::default();
-    lp.timeout = None;
+    if let Some(labels) = selector {
+        lp = lp.labels(labels);
+    }
+    if let Some(fields) = field_selector {
+        lp = lp.fields(fields);
+    }
+
+    lp
+}
+
+pub async fn get_pods(
+    ctx: &KubeContext,
+    namespace: Option<&str>,
+    lp: &ListParams,
+) -> Result<Vec<Pod>> {
+    let pods = ctx.pods_in(namespace);
+
+    Ok(ctx.request(pods.list(lp)).await?.into_iter().collect())
+}
+
+pub async fn describe_pod(ctx: &KubeContext, namespace: &str, pod_name: &str) -> Result<String> {
+    let pods = ctx.pods(namespace);
+
+    let pod: Pod = ctx.request(pods.get(pod_name)).await?;
+
+    let events: Api<Event> = Api::namespaced(ctx.client(), namespace);
+    let lp = ListParams::default().fields(&format!(
+        "involvedObject.kind=Pod,involvedObject.name={}",
+        pod_name
+    ));
+    let mut events: Vec<Event> = ctx.request(events.list(&lp)).await?.into_iter().collect();
+    events.sort_by_key(|e| e.last_timestamp.as_ref().map(|t| t.0));
+    let recent = events.len().saturating_sub(20);
+
+    describe::describe_pod(&pod, &events[recent..])
+}
+
+/// Fetches the previous instance's logs of every container that has terminated at least once.
+pub async fn crash_report(
+    ctx: &KubeContext,
+    namespace: &str,
+    pod_name: &str,
+    tail_lines: i64,
+) -> Result<String> {
+    let pods = ctx.pods(namespace);
+    let pod: Pod = ctx.request(pods.get(pod_name)).await?;
+
+    let mut logs = vec![];
+    for name in describe::crashed_containers(&pod) {
+        let mut lp = LogParams::default();
+        lp.container = Some(name.clone());
+        lp.previous = true;
+        lp.tail_lines = Some(tail_lines);
+        let text = ctx
+            .request(pods.logs(pod_name, &lp))
+            .await
+            .unwrap_or_else(|e| format!("<previous logs unavailable: {}>", e));
+        logs.push((name, text));
+    }
+
+    describe::crash_report(&pod, &logs)
+}
+
+/// Deletes a pod, waiting `grace_period` seconds for it to stop, or the pod's own
+/// terminationGracePeriodSeconds when `None`.
+pub async fn delete_pod(
+    ctx: &KubeContext,
+    namespace: &str,
+    pod_name: &str,
+    grace_period: Option<u32>,
+) -> Result<()> {
+    let pods = ctx.pods(namespace);
+    let dp = DeleteParams {
+        grace_period_seconds: grace_period,
+        ..DeleteParams::default()
+    };
+    ctx.request(pods.delete(pod_name, &dp)).await?;
 
-    Ok(pods.list(&lp).await?.into_iter().collect())
+    Ok(())
+}
+
+/// Does what `kubectl rollout restart` does to the workload owning a pod, given the pod's
+/// controller. A ReplicaSet is followed up to its Deployment. Returns the restarted workload.
+pub async fn restart_owner(
+    ctx: &KubeContext,
+    namespace: &str,
+    kind: &str,
+    name: &str,
+) -> Result<String> {
+    let client = ctx.client();
+    match kind {
+        "ReplicaSet" => {
+            let sets: Api<ReplicaSet> = Api::namespaced(client.clone(), namespace);
+            let rs = ctx.request(sets.get(name)).await?;
+            let owner = rs
+                .metadata
+                .owner_references
+                .unwrap_or_default()
+                .into_iter()
+                .find(|o| o.controller == Some(true) && o.kind == "Deployment")
+                .ok_or_else(|| anyhow!("ReplicaSet {} is not owned by a Deployment", name))?;
+            let api: Api<Deployment> = Api::namespaced(client, namespace);
+            restart_template(ctx, &api, &owner.name).await?;
+            Ok(format!("Deployment/{}", owner.name))
+        }
+        "Deployment" => {
+            let api: Api<Deployment> = Api::namespaced(client, namespace);
+            restart_template(ctx, &api, name).await?;
+            Ok(format!("Deployment/{}", name))
+        }
+        "StatefulSet" => {
+            let api: Api<StatefulSet> = Api::namespaced(client, namespace);
+            restart_template(ctx, &api, name).await?;
+            Ok(format!("StatefulSet/{}", name))
+        }
+        "DaemonSet" => {
+            let api: Api<DaemonSet> = Api::namespaced(client, namespace);
+            restart_template(ctx, &api, name).await?;
+            Ok(format!("DaemonSet/{}", name))
+        }
+        _ => Err(anyhow!("cannot restart pods owned by a {}", kind)),
+    }
 }
 
-pub async fn get_context() -> Result<String> {
-    let conf = Kubeconfig::read()?;
-    Ok(conf.current_context)
+/// Stamps the pod template with the restart annotation kubectl uses, which rolls every pod.
+async fn restart_template<K>(ctx: &KubeContext, api: &Api<K>, name: &str) -> Result<()>
+where
+    K: Clone + DeserializeOwned + Meta,
+{
+    let patch = json!({
+        "spec": {
+            "template": {
+                "metadata": {
+                    "annotations": {
+                        "kubectl.kubernetes.io/restartedAt": Utc::now().to_rfc3339()
+                    }
+                }
+            }
+        }
+    });
+    ctx.request(api.patch(name, &merge_patch(), serde_json::to_vec(&patch)?))
+        .await?;
+
+    Ok(())
 }
 
-pub async fn describe_pod(namespace: &str, pod_name: &str) -> Result<String> {
-    let mut client_config = Config::infer().await?;
-    client_config.timeout = Some(std::time::Duration::from_secs(60 * 10));
-    let client = Client::new(client_config);
+/// Sets the replicas of a Deployment or StatefulSet through its scale subresource.
+pub async fn scale_workload(
+    ctx: &KubeContext,
+    namespace: &str,
+    kind: &str,
+    name: &str,
+    replicas: i32,
+) -> Result<()> {
+    let patch = serde_json::to_vec(&json!({ "spec": { "replicas": replicas } }))?;
+    let client = ctx.client();
+    match kind {
+        "Deployment" => {
+            let api: Api<Deployment> = Api::namespaced(client, namespace);
+            ctx.request(api.patch_scale(name, &merge_patch(), patch))
+                .await?;
+        }
+        "StatefulSet" => {
+            let api: Api<StatefulSet> = Api::namespaced(client, namespace);
+            ctx.request(api.patch_scale(name, &merge_patch(), patch))
+                .await?;
+        }
+        _ => return Err(anyhow!("cannot scale a {}", kind)),
+    }
 
-    let pods: Api<Pod> = Api::namespaced(client, namespace);
+    Ok(())
+}
 
-    let pod: Pod = pods.get(pod_name).await?;
+fn merge_patch() -> PatchParams {
+    PatchParams {
+        patch_strategy: PatchStrategy::Merge,
+        ..PatchParams::default()
+    }
+}
 
-    if let Some(spec) = pod.spec {
-        Ok(format!("{:#?}", spec))
+pub fn format_age(ts: &DateTime<Utc>) -> String {
+    let age = Utc::now().signed_duration_since(*ts);
+    if age.num_days() > 0 {
+        format!("{}d", age.num_days())
+    } else if age.num_hours() > 0 {
+        format!("{}h", age.num_hours())
+    } else if age.num_minutes() > 0 {
+        format!("{}m", age.num_minutes())
     } else {
-        Ok(String::new())
+        format!("{}s", age.num_seconds().max(0))
+    }
+}
+
+/// Parses durations such as `90s`, `15m`, `1h30m` or `2d`; a bare number is taken as seconds.
+pub fn parse_duration(s: &str) -> Result<Duration> {
+    let invalid = || anyhow!("invalid duration '{}'", s);
+    if let Ok(secs) = s.parse::<i64>() {
+        return seconds(secs).ok_or_else(invalid);
+    }
+
+    let mut total = Duration::zero();
+    let mut digits = String::new();
+    for c in s.chars() {
+        if c.is_ascii_digit() {
+            digits.push(c);
+            continue;
+        }
+
+        let n: i64 = digits.parse().map_err(|_| invalid())?;
+        let unit = match c {
+            's' => 1,
+            'm' => 60,
+            'h' => 60 * 60,
+            'd' => 24 * 60 * 60,
+            _ => return Err(invalid()),
+        };
+        total = n
+            .checked_mul(unit)
+            .and_then(seconds)
+            .and_then(|d| total.checked_add(&d))
+            .ok_or_else(invalid)?;
+        digits.clear();
+    }
+
+    if !digits.is_empty() || s.is_empty() {
+        return Err(invalid());
+    }
+
+    Ok(total)
+}
+
+/// `Duration::seconds` panics past the range of a `Duration`, which user input can reach.
+fn seconds(n: i64) -> Option<Duration> {
+    if n.checked_abs()? > Duration::max_value().num_seconds() {
+        return None;
+    }
+
+    Some(Duration::seconds(n))
+}
+
+/// Parses a duration as with `parse_duration` and returns the time that long ago.
+pub fn parse_ago(s: &str) -> Result<DateTime<Utc>> {
+    Utc::now()
+        .checked_sub_signed(parse_duration(s)?)
+        .ok_or_else(|| anyhow!("invalid duration '{}'", s))
+}
+
+/// Parses sizes such as `512K`, `100M` or `1G`; a bare number is taken as bytes.
+pub fn parse_size(s: &str) -> Result<u64> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (n, unit) = s.split_at(split);
+    let n: u64 = n.parse().map_err(|_| anyhow!("invalid size '{}'", s))?;
+    let multiplier = match unit.to_uppercase().trim_end_matches('B') {
+        "" => 1,
+        "K" => 1 << 10,
+        "M" => 1 << 20,
+        "G" => 1 << 30,
+        _ => return Err(anyhow!("invalid size '{}'", s)),
+    };
+
+    n.checked_mul(multiplier)
+        .ok_or_else(|| anyhow!("size '{}' is too large", s))
+}
+
+/// Parses an RFC3339 timestamp, or a duration which is taken as that long ago.
+pub fn parse_time(s: &str) -> Result<DateTime<Utc>> {
+    match DateTime::parse_from_rfc3339(s) {
+        Ok(t) => Ok(t.with_timezone(&Utc)),
+        Err(_) => parse_ago(s)
+            .map_err(|_| anyhow!("invalid time '{}', expected RFC3339 or a duration", s)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_durations() {
+        assert_eq!(parse_duration("90").unwrap(), Duration::seconds(90));
+        assert_eq!(parse_duration("90s").unwrap(), Duration::seconds(90));
+        assert_eq!(parse_duration("15m").unwrap(), Duration::minutes(15));
+        assert_eq!(parse_duration("2h").unwrap(), Duration::hours(2));
+        assert_eq!(parse_duration("2d").unwrap(), Duration::days(2));
+        assert_eq!(
+            parse_duration("1h30m").unwrap(),
+            Duration::hours(1) + Duration::minutes(30)
+        );
+        assert_eq!(
+            parse_duration("1d2h3m4s").unwrap(),
+            Duration::seconds(93_784)
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_durations() {
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("h").is_err());
+        assert!(parse_duration("10x").is_err());
+        assert!(parse_duration("1h30").is_err());
+        assert!(parse_duration("1.5h").is_err());
+        assert!(parse_duration("99999999999999999999s").is_err());
+        assert!(parse_duration("9223372036854775807").is_err());
+        assert!(parse_duration("9223372036854775807d").is_err());
+        assert!(parse_duration("100000000000000d").is_err());
+        assert!(parse_duration("9000000000000000s9000000000000000s").is_err());
+    }
+
+    #[test]
+    fn parses_times() {
+        let t = parse_time("2021-03-04T05:06:07Z").unwrap();
+        assert_eq!(t.to_rfc3339(), "2021-03-04T05:06:07+00:00");
+        let t = parse_time("2021-03-04T07:06:07+02:00").unwrap();
+        assert_eq!(t.to_rfc3339(), "2021-03-04T05:06:07+00:00");
+
+        let ago = Utc::now() - parse_time("1h30m").unwrap();
+        assert!(ago >= Duration::minutes(90) && ago < Duration::minutes(91));
+
+        assert!(parse_time("").is_err());
+        assert!(parse_time("yesterday").is_err());
+        assert!(parse_time("2021-03-04").is_err());
+        assert!(parse_time("100000000000d").is_err());
+    }
+
+    #[test]
+    fn parses_sizes() {
+        assert_eq!(parse_size("512").unwrap(), 512);
+        assert_eq!(parse_size("512B").unwrap(), 512);
+        assert_eq!(parse_size("512K").unwrap(), 512 << 10);
+        assert_eq!(parse_size("100MB").unwrap(), 100 << 20);
+        assert_eq!(parse_size("1g").unwrap(), 1 << 30);
+        assert_eq!(parse_size(" 2G ").unwrap(), 2 << 30);
+    }
+
+    #[test]
+    fn rejects_invalid_sizes() {
+        assert!(parse_size("").is_err());
+        assert!(parse_size("K").is_err());
+        assert!(parse_size("10T").is_err());
+        assert!(parse_size("1.5M").is_err());
+        assert!(parse_size("-1").is_err());
+        assert!(parse_size("99999999999999999999").is_err());
+        assert!(parse_size("17179869184G").is_err());
     }
 }
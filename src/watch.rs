@@ -0,0 +1,134 @@
+use futures::StreamExt;
+use k8s_openapi::api::core::v1::Pod;
+use kube::{
+    api::{ListParams, Meta, WatchEvent},
+    error::ErrorResponse,
+    Api,
+};
+use serde::de::DeserializeOwned;
+use std::time::Duration;
+use tokio::{sync::mpsc, time::delay_for};
+
+use crate::context::KubeContext;
+
+const WATCH_TIMEOUT: u32 = 290;
+const WATCH_BACKOFF_MIN: Duration = Duration::from_secs(1);
+const WATCH_BACKOFF_MAX: Duration = Duration::from_secs(30);
+
+#[derive(Clone, Debug)]
+pub enum ResourceEvent<K> {
+    /// The full set of objects after a (re)list; replaces anything seen before.
+    Restarted(Vec<K>),
+    Applied(K),
+    Deleted(K),
+    /// The watch reconnected without missing any changes.
+    Synced,
+    Error(String),
+}
+
+pub type PodEvent = ResourceEvent<Pod>;
+
+/// Lists and then watches pods in `namespace` (all namespaces when `None`).
+pub fn watch_pods(
+    ctx: &KubeContext,
+    namespace: Option<&str>,
+    lp: ListParams,
+    tx: mpsc::Sender<PodEvent>,
+) {
+    watch_resources(ctx, ctx.pods_in(namespace), lp, tx);
+}
+
+/// Lists and then watches the objects of `api`, resuming from the last seen resourceVersion
+/// whenever the watch drops and relisting once that version has expired. The task ends once
+/// the receiving side of `tx` is dropped.
+pub fn watch_resources<K>(
+    ctx: &KubeContext,
+    api: Api<K>,
+    lp: ListParams,
+    mut tx: mpsc::Sender<ResourceEvent<K>>,
+) where
+    K: Clone + DeserializeOwned + Meta + Send + 'static,
+{
+    let ctx = ctx.clone();
+    tokio::spawn(async move {
+        let mut backoff = WATCH_BACKOFF_MIN;
+        'list: loop {
+            let list = match ctx.request(api.list(&lp)).await {
+                Ok(l) => l,
+                Err(e) => {
+                    if tx.send(ResourceEvent::Error(e.to_string())).await.is_err() {
+                        return;
+                    }
+                    delay_for(backoff).await;
+                    backoff = (backoff * 2).min(WATCH_BACKOFF_MAX);
+                    continue;
+                }
+            };
+
+            backoff = WATCH_BACKOFF_MIN;
+            let mut version = list.metadata.resource_version.clone().unwrap_or_default();
+            if tx.send(ResourceEvent::Restarted(list.items)).await.is_err() {
+                return;
+            }
+
+            loop {
+                let wp = lp.clone().timeout(WATCH_TIMEOUT);
+                let mut stream = match api.watch(&wp, &version).await {
+                    Ok(s) => s.boxed(),
+                    // 410 Gone: our resourceVersion has been compacted away.
+                    Err(kube::Error::Api(ErrorResponse { code: 410, .. })) => continue 'list,
+                    Err(e) => {
+                        if tx.send(ResourceEvent::Error(e.to_string())).await.is_err() {
+                            return;
+                        }
+                        delay_for(backoff).await;
+                        backoff = (backoff * 2).min(WATCH_BACKOFF_MAX);
+                        continue;
+                    }
+                };
+                if tx.send(ResourceEvent::Synced).await.is_err() {
+                    return;
+                }
+
+                // Only a watch that delivers something counts as recovered; one failing on
+                // every attempt is retried with the same backoff as a failed request.
+                while let Some(event) = stream.next().await {
+                    let event = match event {
+                        Ok(WatchEvent::Added(o)) | Ok(WatchEvent::Modified(o)) => {
+                            version = Meta::resource_ver(&o).unwrap_or(version);
+                            ResourceEvent::Applied(o)
+                        }
+                        Ok(WatchEvent::Deleted(o)) => {
+                            version = Meta::resource_ver(&o).unwrap_or(version);
+                            ResourceEvent::Deleted(o)
+                        }
+                        // 410 Gone: our resourceVersion has been compacted away.
+                        Ok(WatchEvent::Error(e)) if e.code == 410 => continue 'list,
+                        Ok(WatchEvent::Error(e)) => {
+                            if tx.send(ResourceEvent::Error(e.message)).await.is_err() {
+                                return;
+                            }
+                            delay_for(backoff).await;
+                            backoff = (backoff * 2).min(WATCH_BACKOFF_MAX);
+                            break;
+                        }
+                        Ok(_) => continue,
+                        Err(e) => {
+                            if tx.send(ResourceEvent::Error(e.to_string())).await.is_err() {
+                                return;
+                            }
+                            delay_for(backoff).await;
+                            backoff = (backoff * 2).min(WATCH_BACKOFF_MAX);
+                            break;
+                        }
+                    };
+
+                    backoff = WATCH_BACKOFF_MIN;
+                    if tx.send(event).await.is_err() {
+                        return;
+                    }
+                }
+            }
+        }
+    });
+}